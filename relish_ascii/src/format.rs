@@ -1,8 +1,6 @@
 use std::fmt::Write;
 
-use relish::TypeId;
-
-use crate::value::Value;
+use relish::{TypeId, Value};
 
 /// Formats a Value as human-readable ASCII text.
 pub fn to_ascii(value: &Value) -> String {
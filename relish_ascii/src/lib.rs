@@ -7,7 +7,6 @@
 
 mod format;
 mod parse_binary;
-mod value;
 
 /// Parses binary relish data and formats it as ASCII.
 pub fn relish2ascii(data: &[u8]) -> Result<String, relish::ParseError> {
@@ -23,7 +22,7 @@ mod tests {
     use crate::format::to_ascii;
     use crate::parse_binary::from_bytes;
     use crate::relish2ascii;
-    use crate::value::Value;
+    use relish::Value;
 
     #[derive(Debug, Relish)]
     struct Person {
@@ -1,6 +1,7 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{Data, DeriveInput, Fields, LitInt, parse_macro_input};
+use syn::ext::IdentExt;
+use syn::{Data, DeriveInput, Fields, GenericParam, LitInt, parse_macro_input};
 
 #[proc_macro_derive(Relish, attributes(relish))]
 pub fn derive_relish(input: TokenStream) -> TokenStream {
@@ -13,10 +14,11 @@ pub fn derive_relish(input: TokenStream) -> TokenStream {
 
 fn derive_relish_expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = &input.ident;
+    let mode = ImplMode::new(&input)?;
 
     match &input.data {
-        Data::Struct(data_struct) => impl_relish_struct(name, data_struct),
-        Data::Enum(data_enum) => impl_relish_enum(name, data_enum),
+        Data::Struct(data_struct) => impl_relish_struct(name, data_struct, &mode),
+        Data::Enum(data_enum) => impl_relish_enum(name, data_enum, &mode),
         Data::Union(_) => Err(syn::Error::new_spanned(
             name,
             "Union types are not supported",
@@ -24,9 +26,66 @@ fn derive_relish_expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStr
     }
 }
 
+/// The paths used by generated code, which differ between owned types
+/// (implementing `Relish`) and types with a lifetime parameter that borrow
+/// from the input (implementing `RelishBorrow<'de>`).
+struct ImplMode {
+    impl_header: proc_macro2::TokenStream,
+    trait_path: proc_macro2::TokenStream,
+    field_value: proc_macro2::TokenStream,
+    parse_fn: proc_macro2::TokenStream,
+    read_field: proc_macro2::TokenStream,
+    parse_tlv: proc_macro2::TokenStream,
+}
+
+impl ImplMode {
+    fn new(input: &DeriveInput) -> syn::Result<Self> {
+        let name = &input.ident;
+        let mut lifetimes = Vec::new();
+
+        for param in &input.generics.params {
+            match param {
+                GenericParam::Lifetime(param) => lifetimes.push(param.lifetime.clone()),
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        param,
+                        "Only a single lifetime parameter is supported",
+                    ));
+                }
+            }
+        }
+
+        match lifetimes.as_slice() {
+            [] => Ok(ImplMode {
+                impl_header: quote! { impl relish::Relish for #name },
+                trait_path: quote! { relish::Relish },
+                field_value: quote! { relish::FieldValue },
+                parse_fn: quote! { fn parse_value(data: &mut relish::BytesRef) },
+                read_field: quote! { read_value_for_field_id },
+                parse_tlv: quote! { parse_tlv },
+            }),
+            [lt] => Ok(ImplMode {
+                impl_header: quote! { impl<#lt> relish::RelishBorrow<#lt> for #name<#lt> },
+                trait_path: quote! { relish::RelishBorrow<#lt> },
+                field_value: quote! { relish::BorrowedFieldValue<#lt> },
+                parse_fn: quote! {
+                    fn parse_borrowed_value(data: &mut relish::BytesRef<#lt>)
+                },
+                read_field: quote! { read_borrowed_value_for_field_id },
+                parse_tlv: quote! { parse_borrowed_tlv },
+            }),
+            _ => Err(syn::Error::new_spanned(
+                &input.generics,
+                "Only a single lifetime parameter is supported",
+            )),
+        }
+    }
+}
+
 fn impl_relish_struct(
     name: &syn::Ident,
     data: &syn::DataStruct,
+    mode: &ImplMode,
 ) -> syn::Result<proc_macro2::TokenStream> {
     let fields = match &data.fields {
         Fields::Named(fields) => &fields.named,
@@ -40,6 +99,7 @@ fn impl_relish_struct(
 
     let mut field_info = Vec::new();
     let mut skipped_fields = Vec::new();
+    let mut unknown_fields_field: Option<syn::Ident> = None;
 
     for field in fields {
         let field_name = field.ident.as_ref().unwrap();
@@ -47,6 +107,7 @@ fn impl_relish_struct(
 
         let mut field_id = None;
         let mut skip = false;
+        let mut unknown_fields = false;
 
         for attr in &field.attrs {
             if attr.path().is_ident("relish") {
@@ -57,6 +118,8 @@ fn impl_relish_struct(
                         field_id = Some(id);
                     } else if meta.path.is_ident("skip") {
                         skip = true;
+                    } else if meta.path.is_ident("unknown_fields") {
+                        unknown_fields = true;
                     } else {
                         return Err(meta.error("unsupported relish attribute"));
                     }
@@ -65,16 +128,29 @@ fn impl_relish_struct(
             }
         }
 
-        if !skip && field_id.is_none() {
+        if [field_id.is_some(), skip, unknown_fields]
+            .iter()
+            .filter(|set| **set)
+            .count()
+            != 1
+        {
             return Err(syn::Error::new_spanned(
                 field_name,
                 format!(
-                    "Field '{field_name}' must have either #[relish(field_id = ...)] or #[relish(skip)]",
+                    "Field '{field_name}' must have exactly one of #[relish(field_id = ...)], #[relish(skip)] or #[relish(unknown_fields)]",
                 ),
             ));
         }
 
-        if skip {
+        if unknown_fields {
+            if unknown_fields_field.is_some() {
+                return Err(syn::Error::new_spanned(
+                    field_name,
+                    "Only one field may have #[relish(unknown_fields)]",
+                ));
+            }
+            unknown_fields_field = Some(field_name.clone());
+        } else if skip {
             skipped_fields.push((field_name.clone(), field_ty.clone()));
         } else {
             field_info.push((field_name.clone(), field_ty.clone(), field_id.unwrap()));
@@ -92,11 +168,35 @@ fn impl_relish_struct(
         }
     }
 
+    let ImplMode {
+        impl_header,
+        trait_path,
+        field_value,
+        parse_fn,
+        read_field,
+        ..
+    } = mode;
+
+    let type_name = name.unraw().to_string();
+    let field_frame = |field_name: &syn::Ident, id: u8| {
+        let field_name = field_name.unraw().to_string();
+        quote! {
+            relish::PathFrame::Field {
+                type_name: #type_name,
+                field_name: #field_name,
+                field_id: #id,
+            }
+        }
+    };
+
     let parse_field_reads: Vec<_> = field_info
         .iter()
         .map(|(name, ty, id)| {
+            let frame = field_frame(name, *id);
             quote! {
-                let #name = parser.read_value_for_field_id::<#ty>(#id)?;
+                let #name = parser
+                    .#read_field::<#ty>(#id)
+                    .map_err(|e| e.with_frame(#frame))?;
             }
         })
         .collect();
@@ -107,9 +207,11 @@ fn impl_relish_struct(
 
     let field_names_len: Vec<_> = field_info.iter().map(|(name, _, _)| name.clone()).collect();
 
-    let field_from_option = field_info.iter().map(|(name, _ty, _)| {
+    let field_from_option = field_info.iter().map(|(name, ty, id)| {
+        let frame = field_frame(name, *id);
         quote! {
-            #name: relish::FieldValue::from_option(#name)?
+            #name: <#ty as #field_value>::from_option(#name)
+                .map_err(|e| e.with_frame(#frame))?
         }
     });
 
@@ -119,52 +221,99 @@ fn impl_relish_struct(
         }
     });
 
+    // Unknown fields are written in the gaps between the known field IDs
+    let (collect_unknown_fields, unknown_fields_init) = match &unknown_fields_field {
+        Some(name) => (
+            quote! { .collect_unknown_fields() },
+            quote! { #name: parser.into_unknown_fields(), },
+        ),
+        None => (quote! {}, quote! {}),
+    };
+    let unknown_fields_gaps = |write: bool| -> Vec<proc_macro2::TokenStream> {
+        let Some(name) = &unknown_fields_field else {
+            return vec![quote! {}; field_info.len() + 1];
+        };
+        let after = std::iter::once(None).chain(field_info.iter().map(|(_, _, id)| Some(*id)));
+        let before = field_info
+            .iter()
+            .map(|(_, _, id)| Some(*id))
+            .chain(std::iter::once(None));
+        after
+            .zip(before)
+            .map(|(after, before)| {
+                let after = option_tokens(after);
+                let before = option_tokens(before);
+                if write {
+                    quote! { self.#name.write_fields_between(buffer, #after, #before)?; }
+                } else {
+                    quote! { content_len += self.#name.fields_length_between(#after, #before); }
+                }
+            })
+            .collect()
+    };
+    let mut unknown_fields_write = unknown_fields_gaps(true);
+    let unknown_fields_write_last = unknown_fields_write.pop().unwrap();
+    let mut unknown_fields_len = unknown_fields_gaps(false);
+    let unknown_fields_len_last = unknown_fields_len.pop().unwrap();
+
     let expanded = quote! {
-        impl relish::Relish for #name {
+        #impl_header {
             const TYPE: relish::TypeId = relish::TypeId::Struct;
 
-            fn parse_value(data: &mut relish::BytesRef) -> relish::ParseResult<Self> {
-                let mut parser = relish::StructParser::new(data);
+            #parse_fn -> relish::ParseResult<Self> {
+                let mut parser = relish::StructReader::new(data)#collect_unknown_fields;
                 #(#parse_field_reads)*
                 parser.finish()?;
 
                 Ok(Self {
                     #(#field_from_option,)*
+                    #unknown_fields_init
                     #(#skipped_field_init),*
                 })
             }
 
-            fn write_value(&self, buffer: &mut Vec<u8>) -> relish::WriteResult<()> {
-                let mut content_len = 0;
-                #(
-                    if let Some(value) = relish::FieldValue::as_relish(&self.#field_names_write) {
-                        content_len += 1 + 1;
-                        content_len += value.value_length();
-                    }
-                )*
-
-                relish::write_tagged_varint_length(buffer, content_len)?;
-
-                #(
-                    if let Some(value) = relish::FieldValue::as_relish(&self.#field_names_write) {
-                        buffer.push(#field_ids_write);
-                        buffer.push(<#field_types_write as relish::FieldValue>::T::TYPE as u8);
-                        value.write_value(buffer)?;
-                    }
-                )*
-
-                Ok(())
+            fn write_value<__W: relish::Sink + ?Sized>(&self, buffer: &mut __W) -> relish::WriteResult<()> {
+                relish::Sink::write_varsize(
+                    buffer,
+                    || {
+                        let mut content_len = 0;
+                        #(
+                            #unknown_fields_len
+                            if let Some(value) = <#field_types_write as #field_value>::as_relish(&self.#field_names_write) {
+                                content_len += 1 + 1;
+                                content_len += <<#field_types_write as #field_value>::T as #trait_path>::value_length(value);
+                            }
+                        )*
+                        #unknown_fields_len_last
+                        content_len
+                    },
+                    |buffer| {
+                        #(
+                            #unknown_fields_write
+                            if let Some(value) = <#field_types_write as #field_value>::as_relish(&self.#field_names_write) {
+                                relish::Sink::write_byte(buffer, #field_ids_write)?;
+                                relish::Sink::write_byte(buffer, <<#field_types_write as #field_value>::T as #trait_path>::value_type(value) as u8)?;
+                                <<#field_types_write as #field_value>::T as #trait_path>::write_value(value, buffer)?;
+                            }
+                        )*
+                        #unknown_fields_write_last
+
+                        Ok(())
+                    },
+                )
             }
 
             fn value_length(&self) -> usize {
-                let mut content_size = 0;
+                let mut content_len = 0;
                 #(
-                    if let Some(value) = relish::FieldValue::as_relish(&self.#field_names_len) {
-                        content_size += 1;
-                        content_size += 1 + value.value_length();
+                    #unknown_fields_len
+                    if let Some(value) = <#field_types_write as #field_value>::as_relish(&self.#field_names_len) {
+                        content_len += 1;
+                        content_len += 1 + <<#field_types_write as #field_value>::T as #trait_path>::value_length(value);
                     }
                 )*
-                relish::tagged_varint_length_size(content_size) + content_size
+                #unknown_fields_len_last
+                relish::tagged_varint_length_size(content_len) + content_len
             }
         }
     };
@@ -175,6 +324,7 @@ fn impl_relish_struct(
 fn impl_relish_enum(
     name: &syn::Ident,
     data: &syn::DataEnum,
+    mode: &ImplMode,
 ) -> syn::Result<proc_macro2::TokenStream> {
     let mut variant_info = Vec::new();
 
@@ -245,10 +395,26 @@ fn impl_relish_enum(
         }
     }
 
+    let ImplMode {
+        impl_header,
+        trait_path,
+        parse_fn,
+        parse_tlv,
+        ..
+    } = mode;
+
+    let type_name = name.unraw().to_string();
     let parse_variants = variant_info.iter().map(|(variant_name, ty, id)| {
+        let variant_name_str = variant_name.unraw().to_string();
         quote! {
             #id => {
-                Self::#variant_name(relish::parse_tlv::<#ty>(data)?)
+                Self::#variant_name(relish::#parse_tlv::<#ty>(data).map_err(|e| {
+                    e.with_frame(relish::PathFrame::Variant {
+                        type_name: #type_name,
+                        variant_name: #variant_name_str,
+                        variant_id: #id,
+                    })
+                })?)
             }
         }
     });
@@ -258,9 +424,9 @@ fn impl_relish_enum(
         .map(|(variant_name, ty, id)| {
             quote! {
                 Self::#variant_name(value) => {
-                    buffer.push(#id);
-                    buffer.push(<#ty as relish::Relish>::TYPE as u8);
-                    value.write_value(buffer)?;
+                    relish::Sink::write_byte(buffer, #id)?;
+                    relish::Sink::write_byte(buffer, <#ty as #trait_path>::value_type(value) as u8)?;
+                    <#ty as #trait_path>::write_value(value, buffer)?;
                 }
             }
         })
@@ -268,20 +434,20 @@ fn impl_relish_enum(
 
     let length_variants: Vec<_> = variant_info
         .iter()
-        .map(|(variant_name, _ty, _id)| {
+        .map(|(variant_name, ty, _id)| {
             quote! {
                 Self::#variant_name(value) => {
-                    1 + 1 + value.value_length()
+                    1 + 1 + <#ty as #trait_path>::value_length(value)
                 }
             }
         })
         .collect();
 
     let expanded = quote! {
-        impl relish::Relish for #name {
+        #impl_header {
             const TYPE: relish::TypeId = relish::TypeId::Enum;
 
-            fn parse_value(data: &mut relish::BytesRef) -> relish::ParseResult<Self> {
+            #parse_fn -> relish::ParseResult<Self> {
                 let field_id = relish::read_byte(data)?;
                 if field_id & 0x80 != 0 {
                     return Err(relish::ParseError::new(
@@ -299,18 +465,20 @@ fn impl_relish_enum(
                 Ok(result)
             }
 
-            fn write_value(&self, buffer: &mut Vec<u8>) -> relish::WriteResult<()> {
-                let content_len = match self {
-                    #(#length_variants)*
-                };
-
-                relish::write_tagged_varint_length(buffer, content_len)?;
-
-                match self {
-                    #(#write_variants)*
-                }
-
-                Ok(())
+            fn write_value<__W: relish::Sink + ?Sized>(&self, buffer: &mut __W) -> relish::WriteResult<()> {
+                relish::Sink::write_varsize(
+                    buffer,
+                    || match self {
+                        #(#length_variants)*
+                    },
+                    |buffer| {
+                        match self {
+                            #(#write_variants)*
+                        }
+
+                        Ok(())
+                    },
+                )
             }
 
             fn value_length(&self) -> usize {
@@ -324,3 +492,10 @@ fn impl_relish_enum(
 
     Ok(expanded)
 }
+
+fn option_tokens(value: Option<u8>) -> proc_macro2::TokenStream {
+    match value {
+        Some(value) => quote! { ::core::option::Option::Some(#value) },
+        None => quote! { ::core::option::Option::None },
+    }
+}
@@ -1,42 +1,153 @@
-use crate::{ParseError, ParseResult};
+use crate::options::{Limit, Limits};
+use crate::{ParseError, ParseErrorKind, ParseResult};
 
 pub struct BytesRef<'a> {
-    b: &'a bytes::Bytes,
+    b: Option<&'a bytes::Bytes>,
     data: &'a [u8],
+    /// Absolute position of `data` within the original input.
+    offset: usize,
+    /// Number of varsize values that enclose `data`.
+    depth: usize,
+    limits: Option<&'a Limits>,
 }
 
 impl<'a> BytesRef<'a> {
     #[doc(hidden)]
     pub fn new(b: &'a bytes::Bytes) -> Self {
-        BytesRef { b, data: b }
+        BytesRef {
+            b: Some(b),
+            data: b,
+            offset: 0,
+            depth: 0,
+            limits: None,
+        }
+    }
+
+    /// Creates a `BytesRef` over a plain slice, with no `Bytes` to share.
+    pub(crate) fn from_slice(data: &'a [u8]) -> Self {
+        BytesRef {
+            b: None,
+            data,
+            offset: 0,
+            depth: 0,
+            limits: None,
+        }
+    }
+
+    /// Returns this `BytesRef` with `limits` applied to everything parsed
+    /// from it.
+    pub(crate) fn with_limits(mut self, limits: &'a Limits) -> Self {
+        self.limits = Some(limits);
+        self
     }
 
     #[doc(hidden)]
     pub fn read(&mut self, amt: usize) -> ParseResult<Self> {
         if amt > self.len() {
-            return Err(ParseError::new(crate::ParseErrorKind::InsufficientData {
+            return Err(ParseError::new(ParseErrorKind::InsufficientData {
                 needed: amt,
                 available: self.len(),
             }));
         }
         let (result, data) = self.data.split_at(amt);
+        let offset = self.offset;
         self.data = data;
+        self.offset += amt;
         Ok(BytesRef {
             b: self.b,
             data: result,
+            offset,
+            depth: self.depth,
+            limits: self.limits,
         })
     }
 
+    /// Reads the `amt` byte content of a varsize value of type `type_id`,
+    /// one level deeper than this one.
+    pub(crate) fn read_varsize(&mut self, type_id: crate::TypeId, amt: usize) -> ParseResult<Self> {
+        if let Some(limits) = self.limits {
+            if self.depth >= limits.options.max_depth {
+                return Err(limit_exceeded(Limit::Depth, limits.options.max_depth));
+            }
+            if type_id == crate::TypeId::String && amt > limits.options.max_string_length {
+                return Err(limit_exceeded(
+                    Limit::StringLength,
+                    limits.options.max_string_length,
+                ));
+            }
+        }
+
+        let mut content = self.read(amt)?;
+        content.depth += 1;
+        Ok(content)
+    }
+
+    /// Accounts for adding an element of type `T` to a collection that
+    /// already holds `count` elements.
+    pub(crate) fn add_element<T>(&self, count: usize) -> ParseResult<()> {
+        if let Some(limits) = self.limits {
+            if count >= limits.options.max_elements {
+                return Err(limit_exceeded(Limit::Elements, limits.options.max_elements));
+            }
+            self.allocate(core::mem::size_of::<T>())?;
+        }
+        Ok(())
+    }
+
+    /// Accounts for allocating `bytes` bytes to hold parsed data.
+    pub(crate) fn allocate(&self, bytes: usize) -> ParseResult<()> {
+        if let Some(limits) = self.limits
+            && !limits.allocate(bytes)
+        {
+            return Err(limit_exceeded(
+                Limit::Allocation,
+                limits.options.max_allocation,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the absolute position of the remaining data within the input
+    /// being parsed.
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the remaining data, borrowed for the lifetime of the
+    /// underlying buffer rather than of this `BytesRef`.
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.data
+    }
+
     /// Returns the remaining data as a zero-copy `Bytes` slice.
+    ///
+    /// When parsing from a plain slice there is no `Bytes` to share, so the
+    /// data is copied instead.
     pub(crate) fn to_bytes(&self) -> bytes::Bytes {
-        self.b.slice_ref(self.data)
+        match self.b {
+            Some(b) => b.slice_ref(self.data),
+            None => bytes::Bytes::copy_from_slice(self.data),
+        }
+    }
+}
+
+impl core::fmt::Debug for BytesRef<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BytesRef")
+            .field("offset", &self.offset)
+            .field("data", &self.data)
+            .finish()
     }
 }
 
-impl std::ops::Deref for BytesRef<'_> {
+impl core::ops::Deref for BytesRef<'_> {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
         self.data
     }
 }
+
+fn limit_exceeded(limit: Limit, max: usize) -> ParseError {
+    ParseError::new(ParseErrorKind::LimitExceeded { limit, max })
+}
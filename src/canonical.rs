@@ -0,0 +1,299 @@
+use alloc::vec::Vec;
+
+use bytes::Bytes;
+
+use crate::buf::BytesRef;
+use crate::options::{Limits, ParseOptions};
+use crate::parse::{check_empty, read_field_id, read_tagged_varint_length};
+use crate::types::TypeLength;
+use crate::write::{Sink, patch_varsize};
+use crate::{
+    ParseError, ParseErrorKind, ParseResult, Relish, RelishBorrow, TypeId, Value, WriteResult,
+};
+
+/// A buffer that values are written to in canonical form.
+pub(crate) struct CanonicalSink(pub(crate) Vec<u8>);
+
+impl Sink for CanonicalSink {
+    fn write_byte(&mut self, byte: u8) -> WriteResult<()> {
+        self.0.write_byte(byte)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> WriteResult<()> {
+        self.0.write_bytes(bytes)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
+
+    fn is_canonical(&self) -> bool {
+        true
+    }
+
+    fn write_varsize(
+        &mut self,
+        _content_len: impl FnOnce() -> usize,
+        write_content: impl FnOnce(&mut Self) -> WriteResult<()>,
+    ) -> WriteResult<()> {
+        let start = self.0.len();
+        self.0.extend_from_slice(&[0; 4]);
+        write_content(self)?;
+        let unused = patch_varsize(&mut self.0[start..])?;
+        self.0.truncate(self.0.len() - unused);
+        Ok(())
+    }
+}
+
+/// Returns the canonical encoding of `value`, without its type ID.
+pub(crate) fn encode_value<T: Relish>(value: &T) -> WriteResult<Vec<u8>> {
+    let mut sink = CanonicalSink(Vec::new());
+    value.write_value(&mut sink)?;
+    Ok(sink.0)
+}
+
+/// Serialize a value to the canonical Relish encoding, in which equal values
+/// always produce identical bytes.
+///
+/// Lengths always use their shortest form, as they do with [`to_vec`](crate::to_vec),
+/// and the entries of maps and sets are sorted by the bytes of their encoded
+/// keys. [`RawValue`](crate::RawValue)s and unknown fields are written as they
+/// were read, so they are only canonical if their input was.
+pub fn to_vec_canonical<'a, T: RelishBorrow<'a>>(value: &T) -> WriteResult<Vec<u8>> {
+    let mut sink = CanonicalSink(Vec::new());
+
+    sink.write_byte(value.value_type() as u8)?;
+
+    value.write_value(&mut sink)?;
+
+    Ok(sink.0)
+}
+
+/// Parse a value from Relish binary format, rejecting input that is not in
+/// the canonical form produced by [`to_vec_canonical`].
+///
+/// Like [`is_canonical`], this limits nesting to [`DEFAULT_MAX_DEPTH`](crate::DEFAULT_MAX_DEPTH) and
+/// fails with [`ParseErrorKind::LimitExceeded`] beyond it.
+pub fn parse_canonical<T: Relish>(data: Bytes) -> ParseResult<T> {
+    check_canonical(&data)?;
+    crate::parse_with_options(data, &ParseOptions::new())
+}
+
+/// Returns whether `data` is a single value in the canonical form produced by
+/// [`to_vec_canonical`]: every length uses its shortest form, and the keys of
+/// every map are sorted by their encoded bytes.
+///
+/// Only the framing is checked, so strings may not be valid UTF-8. Values
+/// nested more than [`DEFAULT_MAX_DEPTH`](crate::DEFAULT_MAX_DEPTH) deep are rejected.
+pub fn is_canonical(data: &[u8]) -> bool {
+    check_canonical(data).is_ok()
+}
+
+fn check_canonical(data: &[u8]) -> ParseResult<()> {
+    let limits = Limits::new(ParseOptions::new());
+    let mut data = BytesRef::from_slice(data).with_limits(&limits);
+    check_tlv(&mut data)?;
+    check_empty(&data)
+}
+
+fn check_tlv(data: &mut BytesRef) -> ParseResult<()> {
+    let offset = data.offset();
+    TypeId::read_for_type::<Value>(data)
+        .and_then(|type_id| check_value(data, type_id))
+        .map_err(|e| e.or_offset(offset))
+}
+
+fn check_value(data: &mut BytesRef, type_id: TypeId) -> ParseResult<()> {
+    let mut content = match type_id.length() {
+        TypeLength::Fixed(size) => {
+            data.read(size)?;
+            return Ok(());
+        }
+        TypeLength::Varsize => {
+            let long_form = data.first().is_some_and(|byte| byte & 0x01 != 0);
+            let length = read_tagged_varint_length(data)? as usize;
+            if long_form && length < 128 {
+                return Err(ParseError::new(ParseErrorKind::NonMinimalLength(length)));
+            }
+            data.read_varsize(type_id, length)?
+        }
+    };
+
+    match type_id {
+        TypeId::Array => {
+            let elem_type = TypeId::read_for_type::<Value>(&mut content)?;
+            while !content.is_empty() {
+                check_element(&mut content, elem_type, None)?;
+            }
+        }
+        TypeId::Map => {
+            let key_type = TypeId::read_for_type::<Value>(&mut content)?;
+            let value_type = TypeId::read_for_type::<Value>(&mut content)?;
+            let mut previous_key = None;
+            while !content.is_empty() {
+                let key = check_element(&mut content, key_type, Some(value_type))?;
+                if previous_key.is_some_and(|previous| previous >= key) {
+                    return Err(ParseError::new(ParseErrorKind::UnsortedMapKeys)
+                        .with_offset(content.offset() - key.len()));
+                }
+                previous_key = Some(key);
+                let offset = content.offset();
+                check_value(&mut content, value_type).map_err(|e| e.or_offset(offset))?;
+            }
+        }
+        TypeId::Struct => {
+            let mut previous_field_id = None;
+            while !content.is_empty() {
+                let offset = content.offset();
+                let field_id = read_field_id(&mut content)?;
+                if let Some(previous) = previous_field_id
+                    && field_id <= previous
+                {
+                    return Err(ParseError::new(ParseErrorKind::FieldOrderViolation {
+                        previous,
+                        current: field_id,
+                    })
+                    .with_offset(offset));
+                }
+                previous_field_id = Some(field_id);
+                check_tlv(&mut content)?;
+            }
+        }
+        TypeId::Enum => {
+            read_field_id(&mut content)?;
+            check_tlv(&mut content)?;
+            check_empty(&content)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Checks an array element or map key, returning its encoding. Elements that
+/// take up no space, along with their map values, cannot fill any remaining
+/// content.
+fn check_element<'a>(
+    content: &mut BytesRef<'a>,
+    type_id: TypeId,
+    value_type: Option<TypeId>,
+) -> ParseResult<&'a [u8]> {
+    let start = content.as_slice();
+    let offset = content.offset();
+    check_value(content, type_id).map_err(|e| e.or_offset(offset))?;
+    let element = &start[..start.len() - content.len()];
+
+    let value_size = value_type.map_or(TypeLength::Fixed(0), TypeId::length);
+    if element.is_empty() && matches!(value_size, TypeLength::Fixed(0)) {
+        return Err(ParseError::new(ParseErrorKind::ExtraData {
+            bytes_remaining: content.len(),
+        })
+        .with_offset(content.offset()));
+    }
+    Ok(element)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use bytes::Bytes;
+
+    use super::{is_canonical, parse_canonical, to_vec_canonical};
+    use crate::{DEFAULT_MAX_DEPTH, Limit, ParseError, ParseErrorKind, Value, parse, to_vec};
+
+    #[test]
+    fn test_to_vec_canonical() {
+        let map: HashMap<String, u8> = (0..32).map(|i| (i.to_string(), i)).collect();
+        let data = to_vec_canonical(&map).unwrap();
+        assert!(is_canonical(&data));
+        assert_eq!(parse_canonical(Bytes::from(data.clone())), Ok(map.clone()));
+
+        // Maps are sorted by encoded key, which is not always their `Ord`
+        let btree_map = map
+            .into_iter()
+            .collect::<std::collections::BTreeMap<_, _>>();
+        assert_eq!(to_vec_canonical(&btree_map).unwrap(), data);
+        assert!(!is_canonical(&to_vec(&btree_map).unwrap()));
+
+        let value = parse::<Value>(Bytes::from(data.clone())).unwrap();
+        let Value::Map { mut entries, .. } = value.clone() else {
+            panic!("expected a map");
+        };
+        entries.reverse();
+        let reversed = Value::Map {
+            key_type: crate::TypeId::String,
+            value_type: crate::TypeId::U8,
+            entries,
+        };
+        assert_eq!(to_vec_canonical(&reversed).unwrap(), data);
+
+        let set: HashSet<u32> = (0..32).collect();
+        let data = to_vec_canonical(&set).unwrap();
+        assert_eq!(data, to_vec_canonical(&set.clone()).unwrap());
+        assert_eq!(parse_canonical(Bytes::from(data)), Ok(set));
+    }
+
+    #[test]
+    fn test_parse_canonical_errors() {
+        // A four byte length for a two byte string
+        let data: &[u8] = &[0x0E, 0x05, 0x00, 0x00, 0x00, b'h', b'i'];
+        assert_eq!(parse::<String>(Bytes::from_static(data)), Ok("hi".into()));
+        assert!(!is_canonical(data));
+        assert_eq!(
+            parse_canonical::<String>(Bytes::from_static(data)),
+            Err(ParseError::new(ParseErrorKind::NonMinimalLength(2)).with_offset(0))
+        );
+
+        // Map keys out of order
+        let data: &[u8] = &[0x10, 0x0C, 0x02, 0x02, 0x02, 0x00, 0x01, 0x00];
+        assert!(parse::<HashMap<u8, u8>>(Bytes::from_static(data)).is_ok());
+        assert_eq!(
+            parse_canonical::<HashMap<u8, u8>>(Bytes::from_static(data)),
+            Err(ParseError::new(ParseErrorKind::UnsortedMapKeys).with_offset(6))
+        );
+
+        // Null elements followed by trailing data
+        assert!(!is_canonical(&[0x0F, 0x04, 0x00, 0xFF]));
+        assert!(is_canonical(&[0x0F, 0x02, 0x00]));
+    }
+
+    /// Returns `depth` enums nested inside each other around a null.
+    fn nested_enums(depth: usize) -> Vec<u8> {
+        // Built back to front, so that each level is only written once
+        let mut reversed = vec![0x00];
+        for _ in 0..depth {
+            let length = reversed.len() + 1;
+            reversed.push(0x00);
+            if length < 128 {
+                reversed.push((length << 1) as u8);
+            } else {
+                reversed.extend((((length << 1) | 1) as u32).to_le_bytes().iter().rev());
+            }
+            reversed.push(0x12);
+        }
+        reversed.reverse();
+        reversed
+    }
+
+    #[test]
+    fn test_canonical_max_depth() {
+        assert!(is_canonical(&nested_enums(DEFAULT_MAX_DEPTH)));
+        assert!(parse_canonical::<Value>(Bytes::from(nested_enums(DEFAULT_MAX_DEPTH))).is_ok());
+
+        let too_deep = ParseErrorKind::LimitExceeded {
+            limit: Limit::Depth,
+            max: DEFAULT_MAX_DEPTH,
+        };
+        let data = nested_enums(DEFAULT_MAX_DEPTH + 1);
+        assert!(!is_canonical(&data));
+        assert_eq!(
+            parse_canonical::<Value>(Bytes::from(data))
+                .unwrap_err()
+                .kind(),
+            &too_deep
+        );
+
+        // Rejected without recursing all the way down
+        assert!(!is_canonical(&nested_enums(200_000)));
+    }
+}
@@ -1,5 +1,24 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
 use thiserror::Error;
 
+use crate::TypeId;
+use crate::options::Limit;
+
+/// Displays a type ID byte by name, or in hex if it is not a valid type ID.
+struct TypeByte(u8);
+
+impl fmt::Display for TypeByte {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match TypeId::from_byte(self.0) {
+            Some(type_id) => write!(f, "{type_id}"),
+            None => write!(f, "{:#04x}", self.0),
+        }
+    }
+}
+
 /// Specific kinds of parsing errors that can occur when deserializing Relish
 /// data.
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
@@ -19,9 +38,24 @@ pub enum ParseErrorKind {
     #[error("duplicate map key")]
     DuplicateMapKey,
 
-    #[error("type mismatch: expected {expected:?}, got {actual:?}")]
+    #[error("duplicate set element")]
+    DuplicateSetElement,
+
+    #[error("expected an array of {expected} elements, got {actual}")]
+    ArrayLengthMismatch { expected: usize, actual: usize },
+
+    #[error("length {0} is not encoded in its shortest form")]
+    NonMinimalLength(usize),
+
+    #[error("map keys are not in canonical order")]
+    UnsortedMapKeys,
+
+    #[error("type mismatch: expected {}, got {}", TypeByte(*expected), TypeByte(*actual))]
     TypeMismatch { expected: u8, actual: u8 },
 
+    #[error("a dynamically typed value cannot be parsed without its type ID")]
+    MissingTypeId,
+
     #[error("invalid boolean value: {0:#02x}")]
     InvalidBoolValue(u8),
 
@@ -37,29 +71,192 @@ pub enum ParseErrorKind {
     #[error("unknown variant ID: {0}")]
     UnknownVariant(u8),
 
-    #[cfg(feature = "chrono")]
-    #[error("invalid timestamp value: {0} (cannot be converted to DateTime)")]
+    #[error("message size {size} exceeds maximum of {max} bytes")]
+    MessageTooLarge { size: usize, max: usize },
+
+    #[error("input ends partway through a record")]
+    TruncatedRecord,
+
+    #[cfg(feature = "std")]
+    #[error("I/O error: {0}")]
+    Io(std::io::ErrorKind),
+
+    #[error("{limit} limit of {max} exceeded")]
+    LimitExceeded { limit: Limit, max: usize },
+
+    #[error("value cannot be encoded: {0}")]
+    InvalidValue(WriteErrorKind),
+
+    #[error("invalid timestamp value: {0} (out of range)")]
     InvalidTimestamp(u64),
+
+    #[error("invalid duration: {0} nanoseconds is not less than one second")]
+    InvalidDurationNanos(u32),
+
+    #[error("invalid char value: {0:#x} (not a Unicode scalar value)")]
+    InvalidChar(u32),
+
+    #[error("zero value for a non-zero integer")]
+    ZeroValue,
+
+    #[cfg(any(feature = "rust_decimal", feature = "url", feature = "semver"))]
+    #[error("string is not a valid {0}")]
+    InvalidFormat(&'static str),
+
+    #[cfg(feature = "serde")]
+    #[error("{0}")]
+    Custom(String),
+}
+
+/// One step in the path from the outermost value to the value that failed to
+/// parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathFrame {
+    /// A field of a derived struct.
+    Field {
+        type_name: &'static str,
+        field_name: &'static str,
+        field_id: u8,
+    },
+    /// A variant of a derived enum.
+    Variant {
+        type_name: &'static str,
+        variant_name: &'static str,
+        variant_id: u8,
+    },
+    /// The element at the given position in an array or tuple.
+    Index(usize),
+    /// The key of the entry at the given position in a map.
+    MapKey(usize),
+    /// The value of the entry at the given position in a map.
+    MapValue(usize),
 }
 
 /// Error type returned when parsing Relish binary data fails.
-#[derive(Error, Debug, Clone, PartialEq, Eq)]
-#[error("parse error: {kind}")]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParseError {
     /// The specific kind of parse error that occurred.
     kind: ParseErrorKind,
+    /// The byte offset of the TLV that failed to parse.
+    offset: Option<usize>,
+    /// The path to the value that failed to parse, innermost first so that
+    /// frames can be pushed as the error propagates outwards.
+    path: Vec<PathFrame>,
 }
 
 impl ParseError {
     /// Creates a new ParseError with the given kind.
     pub const fn new(kind: ParseErrorKind) -> Self {
-        Self { kind }
+        Self {
+            kind,
+            offset: None,
+            path: Vec::new(),
+        }
+    }
+
+    /// Returns this error with its offset set to `offset`.
+    pub const fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Returns this error with `frame` added as the new outermost step of its
+    /// path. Implementations of [`Relish`](crate::Relish) for containers call
+    /// this as errors from their contents propagate outwards.
+    pub fn with_frame(mut self, frame: PathFrame) -> Self {
+        self.path.push(frame);
+        self
     }
 
     /// Returns the specific kind of parse error that occurred.
     pub fn kind(&self) -> &ParseErrorKind {
         &self.kind
     }
+
+    /// Returns the byte offset within the input of the innermost TLV that
+    /// failed to parse, if known.
+    ///
+    /// For values with a type ID this is the offset of the type ID; for
+    /// array elements and map entries, which share a single type ID, it is the
+    /// offset of the element itself. Errors from
+    /// [`StreamReader`](crate::StreamReader) are relative to the start of the
+    /// message being read.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// Returns the path from the outermost value to the value that failed to
+    /// parse. This is empty if the error was not inside a struct, enum, array
+    /// or map.
+    pub fn path(&self) -> impl DoubleEndedIterator<Item = &PathFrame> + ExactSizeIterator {
+        self.path.iter().rev()
+    }
+
+    /// Attributes this error to the TLV at `offset`, unless a nested TLV has
+    /// already claimed it.
+    pub(crate) fn or_offset(mut self, offset: usize) -> Self {
+        self.offset.get_or_insert(offset);
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("parse error")?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {offset}")?;
+        }
+        f.write_str(": ")?;
+
+        if !self.path.is_empty() {
+            for (i, frame) in self.path().enumerate() {
+                match frame {
+                    PathFrame::Field {
+                        type_name,
+                        field_name,
+                        ..
+                    } => {
+                        if i == 0 {
+                            f.write_str(type_name)?;
+                        }
+                        write!(f, ".{field_name}")?;
+                    }
+                    PathFrame::Variant {
+                        type_name,
+                        variant_name,
+                        ..
+                    } => {
+                        if i == 0 {
+                            f.write_str(type_name)?;
+                        }
+                        write!(f, "::{variant_name}")?;
+                    }
+                    PathFrame::Index(index) => write!(f, "[{index}]")?,
+                    PathFrame::MapKey(index) => write!(f, "[key {index}]")?,
+                    PathFrame::MapValue(index) => write!(f, "[value {index}]")?,
+                }
+            }
+            match self.path.first() {
+                Some(PathFrame::Field { field_id, .. }) => write!(f, " (field {field_id})")?,
+                Some(PathFrame::Variant { variant_id, .. }) => {
+                    write!(f, " (variant {variant_id})")?
+                }
+                _ => {}
+            }
+            f.write_str(": ")?;
+        }
+
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for ParseError {
+    fn from(e: std::io::Error) -> Self {
+        ParseError::new(ParseErrorKind::Io(e.kind()))
+    }
 }
 
 /// Result type for parsing operations.
@@ -71,12 +268,53 @@ pub enum WriteErrorKind {
     #[error("field ID {0} is too large (top bit would be set)")]
     FieldIdTooLarge(u8),
 
+    #[error(
+        "field IDs must be written in strictly increasing order: got {current} after {previous}"
+    )]
+    FieldOrderViolation { previous: u8, current: u8 },
+
+    #[error("duplicate map key")]
+    DuplicateMapKey,
+
     #[error("content length {0} exceeds maximum allowed (u32::MAX >> 1)")]
     ContentTooLarge(usize),
 
-    #[cfg(feature = "chrono")]
+    #[cfg(feature = "std")]
+    #[error("I/O error: {0}")]
+    Io(std::io::ErrorKind),
+
+    #[error(
+        "array and map elements must share a type: expected {}, got {}",
+        TypeByte(*expected),
+        TypeByte(*actual)
+    )]
+    MixedElementTypes { expected: u8, actual: u8 },
+
+    #[error("written data does not parse as a value: {0}")]
+    InvalidEncoding(Box<ParseErrorKind>),
+
     #[error("timestamp cannot be serialized as a unix timestamp")]
     InvalidTimestamp,
+
+    #[error("path is not valid UTF-8")]
+    NonUtf8Path,
+
+    #[error("buffer too small: need {needed} bytes, have {available}")]
+    BufferTooSmall { needed: usize, available: usize },
+
+    #[error("value wrote different content when measured and when written")]
+    InconsistentValue,
+
+    #[error("message size {size} exceeds maximum of {max} bytes")]
+    MessageTooLarge { size: usize, max: usize },
+
+    #[cfg(feature = "serde")]
+    #[error("duplicate field ID: {0}")]
+    DuplicateFieldId(u8),
+
+    #[cfg(feature = "serde")]
+    #[error("{0}")]
+    Custom(String),
 }
 
 /// Error type returned when serializing to Relish binary format fails.
@@ -99,5 +337,12 @@ impl WriteError {
     }
 }
 
+#[cfg(feature = "std")]
+impl From<std::io::Error> for WriteError {
+    fn from(e: std::io::Error) -> Self {
+        WriteError::new(WriteErrorKind::Io(e.kind()))
+    }
+}
+
 /// Result type for writing operations.
 pub type WriteResult<T> = Result<T, WriteError>;
@@ -0,0 +1,266 @@
+use alloc::vec::Vec;
+
+use bytes::Bytes;
+
+use crate::buf::BytesRef;
+use crate::parse::{
+    StructReader, check_empty, read_byte, read_type_and_value, read_value_for_typeid,
+};
+use crate::types::TypeLength;
+use crate::{ParseError, ParseErrorKind, ParseResult, Relish, TypeId, Value};
+
+/// One step of the path to the value that [`extract`] decodes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathStep {
+    /// The struct field with the given ID.
+    Field(u8),
+    /// The value of an enum, if it holds the variant with the given ID.
+    Variant(u8),
+    /// The array element at the given index.
+    Index(usize),
+    /// The value of the map entry whose key encodes identically to the given
+    /// key.
+    Key(Value),
+}
+
+impl PathStep {
+    fn container_type(&self) -> TypeId {
+        match self {
+            PathStep::Field(_) => TypeId::Struct,
+            PathStep::Variant(_) => TypeId::Enum,
+            PathStep::Index(_) => TypeId::Array,
+            PathStep::Key(_) => TypeId::Map,
+        }
+    }
+}
+
+/// A value that has been located but not parsed: the offset of its type ID
+/// (or of the value itself, for array elements and map values), its type, and
+/// its encoded value.
+type Located<'a> = (usize, TypeId, BytesRef<'a>);
+
+/// Decodes the value at `path` within the message in `data`, without decoding
+/// anything else.
+///
+/// Each step descends into a struct field, enum variant, array element or map
+/// value, and everything it passes over is skipped using its length prefix.
+/// Returns `Ok(None)` if a field, element or map key is absent, or an enum
+/// holds a different variant. A step that does not match the type of the
+/// value it is applied to fails with [`ParseErrorKind::TypeMismatch`].
+///
+/// ```
+/// use bytes::Bytes;
+/// use relish::PathStep;
+///
+/// let data = Bytes::from(relish::to_vec(&(1u8, vec!["a".to_string(), "b".to_string()])).unwrap());
+/// let value: Option<String> =
+///     relish::extract(&data, &[PathStep::Field(1), PathStep::Index(1)]).unwrap();
+/// assert_eq!(value.as_deref(), Some("b"));
+/// ```
+pub fn extract<T: Relish>(data: &Bytes, path: &[PathStep]) -> ParseResult<Option<T>> {
+    let mut data = BytesRef::new(data);
+    let (mut type_id, mut value) = read_type_and_value(&mut data)?;
+    check_empty(&data)?;
+    let mut offset = 0;
+
+    for step in path {
+        let expected = step.container_type();
+        if type_id != expected {
+            return Err(ParseError::new(ParseErrorKind::TypeMismatch {
+                expected: expected as u8,
+                actual: type_id as u8,
+            })
+            .with_offset(offset));
+        }
+
+        let content_offset = value.offset();
+        let next = match step {
+            PathStep::Field(field_id) => seek_field(&mut value, *field_id),
+            PathStep::Variant(variant_id) => seek_variant(&mut value, *variant_id),
+            PathStep::Index(index) => seek_element(&mut value, *index),
+            PathStep::Key(key) => seek_map_value(&mut value, key),
+        }
+        .map_err(|e| e.or_offset(content_offset))?;
+
+        match next {
+            Some((next_offset, next_type_id, next_value)) => {
+                offset = next_offset;
+                type_id = next_type_id;
+                value = next_value;
+            }
+            None => return Ok(None),
+        }
+    }
+
+    if !T::accepts_type(type_id) {
+        return Err(ParseError::new(ParseErrorKind::TypeMismatch {
+            expected: T::TYPE as u8,
+            actual: type_id as u8,
+        })
+        .with_offset(offset));
+    }
+    T::parse_typed_value(type_id, &mut value)
+        .map(Some)
+        .map_err(|e| e.or_offset(offset))
+}
+
+fn locate<'a>(data: &mut BytesRef<'a>) -> ParseResult<Located<'a>> {
+    let offset = data.offset();
+    let (type_id, value) = read_type_and_value(data)?;
+    Ok((offset, type_id, value))
+}
+
+fn seek_field<'a>(data: &mut BytesRef<'a>, field_id: u8) -> ParseResult<Option<Located<'a>>> {
+    match StructReader::new(data).seek_raw_field(field_id)? {
+        Some(field_data) => locate(field_data).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn seek_variant<'a>(data: &mut BytesRef<'a>, variant_id: u8) -> ParseResult<Option<Located<'a>>> {
+    if read_byte(data)? != variant_id {
+        return Ok(None);
+    }
+    locate(data).map(Some)
+}
+
+fn seek_element<'a>(data: &mut BytesRef<'a>, index: usize) -> ParseResult<Option<Located<'a>>> {
+    let element_type = TypeId::read_for_type::<Value>(data)?;
+
+    if let TypeLength::Fixed(size) = element_type.length() {
+        // Fixed-size elements can be skipped all at once.
+        if size == 0 || index >= data.len() / size {
+            return Ok(None);
+        }
+        data.read(index * size)?;
+    } else {
+        for _ in 0..index {
+            if data.is_empty() {
+                return Ok(None);
+            }
+            let offset = data.offset();
+            read_value_for_typeid(data, element_type).map_err(|e| e.or_offset(offset))?;
+        }
+    }
+
+    if data.is_empty() {
+        return Ok(None);
+    }
+    let offset = data.offset();
+    let element = read_value_for_typeid(data, element_type).map_err(|e| e.or_offset(offset))?;
+    Ok(Some((offset, element_type, element)))
+}
+
+fn seek_map_value<'a>(data: &mut BytesRef<'a>, key: &Value) -> ParseResult<Option<Located<'a>>> {
+    let key_type = TypeId::read_for_type::<Value>(data)?;
+    let value_type = TypeId::read_for_type::<Value>(data)?;
+    if key.value_type() != key_type {
+        return Ok(None);
+    }
+
+    let mut encoded_key = Vec::new();
+    key.write_value(&mut encoded_key)
+        .map_err(|e| ParseError::new(ParseErrorKind::InvalidValue(e.kind().clone())))?;
+
+    while !data.is_empty() {
+        let offset = data.offset();
+        let key_bytes = data.as_slice();
+        read_value_for_typeid(data, key_type).map_err(|e| e.or_offset(offset))?;
+        let key_bytes = &key_bytes[..key_bytes.len() - data.len()];
+
+        let offset = data.offset();
+        let value = read_value_for_typeid(data, value_type).map_err(|e| e.or_offset(offset))?;
+        if key_bytes == encoded_key {
+            return Ok(Some((offset, value_type, value)));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bytes::Bytes;
+
+    use super::{PathStep, extract};
+    use crate::{ParseError, ParseErrorKind, Value, to_vec};
+
+    #[test]
+    fn test_extract() {
+        let map = BTreeMap::from([("a".to_string(), vec![1u32, 2]), ("b".to_string(), vec![3])]);
+        let result: Result<u8, String> = Err("no".to_string());
+        let data = Bytes::from(
+            to_vec(&(
+                7u8,
+                vec!["x".to_string(), "y".to_string(), "z".to_string()],
+                map,
+                result,
+            ))
+            .unwrap(),
+        );
+
+        let cases: &[(&[PathStep], Option<Value>)] = &[
+            (&[PathStep::Field(0)], Some(Value::U8(7))),
+            (
+                &[PathStep::Field(1), PathStep::Index(2)],
+                Some(Value::String("z".to_string())),
+            ),
+            (&[PathStep::Field(1), PathStep::Index(3)], None),
+            (
+                &[
+                    PathStep::Field(2),
+                    PathStep::Key(Value::String("a".to_string())),
+                    PathStep::Index(1),
+                ],
+                Some(Value::U32(2)),
+            ),
+            (
+                &[
+                    PathStep::Field(2),
+                    PathStep::Key(Value::String("c".to_string())),
+                ],
+                None,
+            ),
+            (
+                &[PathStep::Field(3), PathStep::Variant(1)],
+                Some(Value::String("no".to_string())),
+            ),
+            (&[PathStep::Field(3), PathStep::Variant(0)], None),
+            (&[PathStep::Field(9)], None),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(extract::<Value>(&data, path).as_ref(), Ok(expected));
+        }
+
+        assert_eq!(
+            extract::<u32>(&data, &[PathStep::Field(0)]),
+            Err(ParseError::new(ParseErrorKind::TypeMismatch {
+                expected: 0x04,
+                actual: 0x02
+            })
+            .with_offset(3))
+        );
+        assert_eq!(
+            extract::<Value>(&data, &[PathStep::Field(0), PathStep::Index(0)]),
+            Err(ParseError::new(ParseErrorKind::TypeMismatch {
+                expected: 0x0F,
+                actual: 0x02
+            })
+            .with_offset(3))
+        );
+    }
+
+    #[test]
+    fn test_extract_skips_invalid_values() {
+        // Only the target is decoded, so invalid UTF-8 elsewhere is ignored
+        let data = Bytes::from_static(&[
+            0x11, 0x14, 0x00, 0x0E, 0x02, 0xFF, 0x01, 0x0F, 0x06, 0x03, 0x01, 0x00,
+        ]);
+        assert_eq!(
+            extract::<u16>(&data, &[PathStep::Field(1), PathStep::Index(0)]),
+            Ok(Some(1))
+        );
+        assert!(extract::<String>(&data, &[PathStep::Field(0)]).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+#[cfg(not(feature = "std"))]
+use core::cell::OnceCell;
+// `OnceLock` keeps `Lazy` `Sync`, so parsed messages can be shared between
+// threads
+#[cfg(feature = "std")]
+use std::sync::OnceLock as OnceCell;
+
+use crate::buf::BytesRef;
+use crate::write::Sink;
+use crate::{ParseOptions, ParseResult, RawValue, Relish, TypeId};
+
+/// A value that is decoded only when it is first accessed.
+///
+/// Parsing a `Lazy<T>` keeps the encoded value as a [`RawValue`] without
+/// checking it. [`get`](Self::get) decodes and caches it, reporting
+/// any errors then; offsets in those errors are relative to the start of the
+/// value. Until the value is changed through [`get_mut`](Self::get_mut), the
+/// original bytes are written back out unchanged.
+///
+/// The options a message was parsed with do not carry over to decoding its
+/// `Lazy` fields; use [`get_with_options`](Self::get_with_options) for
+/// untrusted input.
+///
+/// ```
+/// #[derive(relish::Relish)]
+/// struct Envelope {
+///     #[relish(field_id = 0)]
+///     kind: String,
+///     #[relish(field_id = 1)]
+///     payload: relish::Lazy<Vec<u64>>,
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Lazy<T> {
+    encoded: Option<RawValue>,
+    value: OnceCell<T>,
+}
+
+impl<T: Relish> Lazy<T> {
+    /// Creates a `Lazy` holding an already decoded value.
+    pub fn new(value: T) -> Self {
+        Lazy {
+            encoded: None,
+            value: OnceCell::from(value),
+        }
+    }
+
+    /// Returns the value, decoding it if this is the first access.
+    pub fn get(&self) -> ParseResult<&T> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+        let value = self.decode(None)?;
+        Ok(self.value.get_or_init(|| value))
+    }
+
+    /// Like [`get`](Self::get), enforcing the resource limits in `options`
+    /// when the value is decoded.
+    pub fn get_with_options(&self, options: &ParseOptions) -> ParseResult<&T> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+        let value = self.decode(Some(options))?;
+        Ok(self.value.get_or_init(|| value))
+    }
+
+    /// Returns the value mutably, decoding it if necessary. The original
+    /// bytes are discarded, so the value is re-encoded when written.
+    pub fn get_mut(&mut self) -> ParseResult<&mut T> {
+        if self.value.get().is_none() {
+            self.value = OnceCell::from(self.decode(None)?);
+        }
+        self.encoded = None;
+        Ok(self.value.get_mut().unwrap())
+    }
+
+    /// Returns the value, decoding it if necessary.
+    pub fn into_inner(mut self) -> ParseResult<T> {
+        match self.value.take() {
+            Some(value) => Ok(value),
+            None => self.decode(None),
+        }
+    }
+
+    /// Returns whether the value has been decoded, or was never encoded.
+    pub fn is_decoded(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    fn decode(&self, options: Option<&ParseOptions>) -> ParseResult<T> {
+        let encoded = self
+            .encoded
+            .as_ref()
+            .expect("Lazy holds either an encoded or a decoded value");
+        match options {
+            Some(options) => encoded.decode_with_options(options),
+            None => encoded.decode(),
+        }
+    }
+}
+
+impl<T: Relish> From<T> for Lazy<T> {
+    fn from(value: T) -> Self {
+        Lazy::new(value)
+    }
+}
+
+impl<T: Relish> Relish for Lazy<T> {
+    const TYPE: TypeId = T::TYPE;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        Self::parse_typed_value(T::TYPE, data)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        match &self.encoded {
+            Some(encoded) => encoded.write_value(buffer),
+            None => self.value.get().unwrap().write_value(buffer),
+        }
+    }
+
+    fn value_length(&self) -> usize {
+        match &self.encoded {
+            Some(encoded) => encoded.value_length(),
+            None => self.value.get().unwrap().value_length(),
+        }
+    }
+
+    fn value_type(&self) -> TypeId {
+        match &self.encoded {
+            Some(encoded) => encoded.value_type(),
+            None => self.value.get().unwrap().value_type(),
+        }
+    }
+
+    fn accepts_type(type_id: TypeId) -> bool {
+        T::accepts_type(type_id)
+    }
+
+    fn parse_typed_value(type_id: TypeId, data: &mut BytesRef) -> ParseResult<Self> {
+        Ok(Lazy {
+            encoded: Some(RawValue::parse_typed_value(type_id, data)?),
+            value: OnceCell::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::Lazy;
+    use crate::{Limit, ParseError, ParseErrorKind, ParseOptions, Value, parse, to_vec};
+
+    #[test]
+    fn test_lazy_roundtrip() {
+        // A string with invalid UTF-8 is only rejected when it is accessed
+        let data = [0x11u8, 0x0E, 0x00, 0x02, 0x07, 0x01, 0x0E, 0x02, 0xFF];
+        let (n, lazy): (u8, Lazy<String>) = parse(Bytes::from(data.to_vec())).unwrap();
+        assert_eq!(n, 7);
+        assert!(!lazy.is_decoded());
+        assert_eq!(to_vec(&(n, lazy.clone())).unwrap(), data);
+        assert_eq!(
+            lazy.get(),
+            Err(ParseError::new(ParseErrorKind::InvalidUtf8))
+        );
+
+        let data = to_vec(&(7u8, "hi".to_string())).unwrap();
+        let (_, mut lazy): (u8, Lazy<String>) = parse(Bytes::from(data.clone())).unwrap();
+        assert_eq!(lazy.get().map(String::as_str), Ok("hi"));
+        assert!(lazy.is_decoded());
+        assert_eq!(to_vec(&(7u8, lazy.clone())).unwrap(), data);
+
+        lazy.get_mut().unwrap().push('!');
+        assert_eq!(
+            to_vec(&(7u8, lazy)).unwrap(),
+            to_vec(&(7u8, "hi!".to_string())).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_lazy_options() {
+        let data = to_vec(&(7u8, vec![vec![1u8]])).unwrap();
+        let (_, lazy): (u8, Lazy<Vec<Vec<u8>>>) = parse(Bytes::from(data)).unwrap();
+        assert_eq!(
+            lazy.get_with_options(&ParseOptions::new().max_depth(1))
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::LimitExceeded {
+                limit: Limit::Depth,
+                max: 1
+            }
+        );
+        assert!(!lazy.is_decoded());
+        assert_eq!(
+            lazy.get_with_options(&ParseOptions::new().max_depth(2)),
+            Ok(&vec![vec![1]])
+        );
+    }
+
+    #[test]
+    fn test_lazy_is_sync() {
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<Lazy<String>>();
+    }
+
+    #[test]
+    fn test_lazy_value() {
+        let data = to_vec(&42u32).unwrap();
+        let lazy: Lazy<Value> = parse(Bytes::from(data.clone())).unwrap();
+        assert_eq!(to_vec(&lazy).unwrap(), data);
+        assert_eq!(lazy.into_inner(), Ok(Value::U32(42)));
+        assert_eq!(to_vec(&Lazy::new(Value::U32(42))).unwrap(), data);
+    }
+}
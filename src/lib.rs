@@ -18,28 +18,67 @@
 //! let parsed: u32 = parse(Bytes::from(bytes)).unwrap();
 //! assert_eq!(parsed, 42);
 //! ```
+//!
+//! # Features
+//!
+//! The `std` feature is enabled by default. Without it the crate is
+//! `#![no_std]` and needs only `alloc`; [`to_writer`], [`from_reader`] and the
+//! `std`-only types such as `HashMap` and `SystemTime` are unavailable.
+//! `HashMap` and `HashSet` support can be restored with the `hashbrown`
+//! feature.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 mod buf;
+mod canonical;
 mod error;
+mod extract;
+mod lazy;
+mod options;
 mod parse;
+mod pull;
+mod raw;
+#[cfg(feature = "std")]
+mod read;
+mod record;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "tokio")]
+pub mod tokio;
 mod traits;
 mod types;
+mod unknown_fields;
+mod value;
 mod write;
 
 pub use crate::buf::BytesRef;
+pub use crate::canonical::{is_canonical, parse_canonical, to_vec_canonical};
 pub use crate::error::{
-    ParseError, ParseErrorKind, ParseResult, WriteError, WriteErrorKind, WriteResult,
+    ParseError, ParseErrorKind, ParseResult, PathFrame, WriteError, WriteErrorKind, WriteResult,
 };
-pub use crate::parse::parse;
-pub use crate::traits::{FieldValue, Relish};
+pub use crate::extract::{PathStep, extract};
+pub use crate::lazy::Lazy;
+pub use crate::options::{DEFAULT_MAX_DEPTH, Limit, ParseOptions};
+pub use crate::parse::{StructReader, parse, parse_borrowed, parse_with_options};
+pub use crate::pull::{Event, Reader};
+pub use crate::raw::RawValue;
+#[cfg(feature = "std")]
+pub use crate::read::{DEFAULT_MAX_MESSAGE_SIZE, StreamReader, from_reader};
+#[cfg(feature = "std")]
+pub use crate::record::RecordWriter;
+pub use crate::record::{RecordReader, RecordSource, Records};
+pub use crate::traits::{BorrowedFieldValue, FieldValue, Relish, RelishBorrow};
 pub use crate::types::{Null, TypeId};
-pub use crate::write::to_vec;
-
-#[doc(hidden)]
-pub use crate::parse::StructParser;
+pub use crate::unknown_fields::UnknownFields;
+pub use crate::value::{Value, from_value, to_value};
+#[cfg(feature = "std")]
+pub use crate::write::to_writer;
+pub use crate::write::{Sink, StructWriter, to_buf_mut, to_slice, to_vec};
 
 #[doc(hidden)]
-pub use crate::parse::{parse_tlv, read_byte, read_value_for_typeid};
+pub use crate::parse::{parse_borrowed_tlv, parse_tlv, read_byte, read_value_for_typeid};
 
 #[doc(hidden)]
 pub use crate::types::{tagged_varint_length_size, write_tagged_varint_length};
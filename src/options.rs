@@ -0,0 +1,245 @@
+use core::cell::Cell;
+use core::fmt;
+
+/// The default maximum nesting depth used by [`ParseOptions`].
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Resource limits applied while parsing, for use with
+/// [`parse_with_options`](crate::parse_with_options).
+///
+/// Only the nesting depth is limited by default; set the other limits when
+/// parsing untrusted input.
+///
+/// ```
+/// let options = relish::ParseOptions::new()
+///     .max_string_length(1 << 20)
+///     .max_elements(10_000)
+///     .max_allocation(16 << 20);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub(crate) max_depth: usize,
+    pub(crate) max_string_length: usize,
+    pub(crate) max_elements: usize,
+    pub(crate) max_allocation: usize,
+}
+
+impl ParseOptions {
+    /// Creates options with a nesting depth limit of [`DEFAULT_MAX_DEPTH`] and
+    /// no other limits.
+    pub const fn new() -> Self {
+        ParseOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_string_length: usize::MAX,
+            max_elements: usize::MAX,
+            max_allocation: usize::MAX,
+        }
+    }
+
+    /// Sets the maximum nesting depth of strings, arrays, maps, structs and
+    /// enums. The outermost value is at depth 1.
+    pub const fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the maximum length of a string, in bytes.
+    pub const fn max_string_length(mut self, max_string_length: usize) -> Self {
+        self.max_string_length = max_string_length;
+        self
+    }
+
+    /// Sets the maximum number of elements in any single array, or entries
+    /// in any single map.
+    pub const fn max_elements(mut self, max_elements: usize) -> Self {
+        self.max_elements = max_elements;
+        self
+    }
+
+    /// Sets the maximum number of bytes that may be allocated for strings and
+    /// collection elements across the whole message.
+    ///
+    /// Zero-copy values such as [`Bytes`](bytes::Bytes) do not count towards
+    /// this budget.
+    pub const fn max_allocation(mut self, max_allocation: usize) -> Self {
+        self.max_allocation = max_allocation;
+        self
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions::new()
+    }
+}
+
+/// The resource limit that was exceeded, reported by
+/// [`ParseErrorKind::LimitExceeded`](crate::ParseErrorKind::LimitExceeded).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Depth,
+    StringLength,
+    Elements,
+    Allocation,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Limit::Depth => "nesting depth",
+            Limit::StringLength => "string length",
+            Limit::Elements => "element count",
+            Limit::Allocation => "allocation",
+        })
+    }
+}
+
+/// The state of a single parse under [`ParseOptions`], shared by every
+/// [`BytesRef`](crate::BytesRef) into the message.
+pub(crate) struct Limits {
+    pub(crate) options: ParseOptions,
+    allocated: Cell<usize>,
+}
+
+impl Limits {
+    pub(crate) fn new(options: ParseOptions) -> Self {
+        Limits {
+            options,
+            allocated: Cell::new(0),
+        }
+    }
+
+    /// Records an allocation of `bytes`, returning whether it fits within the
+    /// budget.
+    pub(crate) fn allocate(&self, bytes: usize) -> bool {
+        let allocated = self.allocated.get().saturating_add(bytes);
+        self.allocated.set(allocated);
+        allocated <= self.options.max_allocation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::{Limit, ParseOptions};
+    use crate::{Null, ParseErrorKind, ParseResult, Value, parse_with_options};
+
+    fn parse_with<T: crate::Relish>(data: &[u8], options: ParseOptions) -> ParseResult<T> {
+        parse_with_options(Bytes::from(data.to_vec()), &options)
+    }
+
+    fn nested_arrays(depth: usize) -> Vec<u8> {
+        // The innermost array is empty, with a Null element type
+        let mut value = vec![0x02, 0x00];
+        for _ in 1..depth {
+            let mut content = vec![0x0F];
+            content.extend_from_slice(&value);
+            value = vec![(content.len() << 1) as u8];
+            value.extend_from_slice(&content);
+        }
+        value.insert(0, 0x0F);
+        value
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let options = ParseOptions::new().max_depth(3);
+        assert!(parse_with::<Value>(&nested_arrays(3), options).is_ok());
+        assert_eq!(
+            parse_with::<Value>(&nested_arrays(4), options)
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::LimitExceeded {
+                limit: Limit::Depth,
+                max: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_max_string_length() {
+        let data = [0x0E, 0x0A, b'H', b'e', b'l', b'l', b'o'];
+        assert_eq!(
+            parse_with::<String>(&data, ParseOptions::new().max_string_length(5)),
+            Ok("Hello".to_string())
+        );
+        assert_eq!(
+            parse_with::<String>(&data, ParseOptions::new().max_string_length(4))
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::LimitExceeded {
+                limit: Limit::StringLength,
+                max: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_zero_size_elements() {
+        // Null elements take no space, so trailing data would otherwise be
+        // parsed as an unbounded number of them
+        let data = [0x0F, 0x04, 0x00, 0xFF];
+        let extra_data = ParseErrorKind::ExtraData { bytes_remaining: 1 };
+        let options = ParseOptions::default();
+        assert_eq!(
+            parse_with::<Vec<Null>>(&data, options).unwrap_err().kind(),
+            &extra_data
+        );
+        assert_eq!(
+            parse_with::<Vec<()>>(&data, options).unwrap_err().kind(),
+            &extra_data
+        );
+        assert_eq!(
+            parse_with::<Vec<std::marker::PhantomData<u8>>>(&data, options)
+                .unwrap_err()
+                .kind(),
+            &extra_data
+        );
+        assert_eq!(
+            parse_with::<Value>(&data, options).unwrap_err().kind(),
+            &extra_data
+        );
+        assert_eq!(
+            crate::parse::<Vec<Null>>(Bytes::copy_from_slice(&data))
+                .unwrap_err()
+                .kind(),
+            &extra_data
+        );
+
+        // Zero-size elements are fine when there is nothing else
+        assert_eq!(
+            parse_with::<Vec<Null>>(&[0x0F, 0x02, 0x00], options),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn test_max_elements() {
+        let data = [0x0F, 0x08, 0x02, 0x01, 0x02, 0x03];
+        let options = ParseOptions::new().max_elements(3);
+        assert_eq!(parse_with::<Vec<u8>>(&data, options), Ok(vec![1, 2, 3]));
+        assert!(parse_with::<Value>(&data, options).is_ok());
+        let options = ParseOptions::new().max_elements(2);
+        assert!(parse_with::<Vec<u8>>(&data, options).is_err());
+        assert!(parse_with::<Value>(&data, options).is_err());
+    }
+
+    #[test]
+    fn test_max_allocation() {
+        let data = crate::to_vec(&vec!["hello".to_string(), "world".to_string()]).unwrap();
+        let needed = 2 * (std::mem::size_of::<String>() + 5);
+        assert!(
+            parse_with::<Vec<String>>(&data, ParseOptions::new().max_allocation(needed)).is_ok()
+        );
+        assert_eq!(
+            parse_with::<Vec<String>>(&data, ParseOptions::new().max_allocation(needed - 1))
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::LimitExceeded {
+                limit: Limit::Allocation,
+                max: needed - 1
+            }
+        );
+    }
+}
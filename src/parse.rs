@@ -1,6 +1,10 @@
 use crate::buf::BytesRef;
+use crate::options::{Limits, ParseOptions};
 use crate::types::TypeLength;
-use crate::{ParseError, ParseErrorKind, ParseResult, Relish, TypeId};
+use crate::{
+    ParseError, ParseErrorKind, ParseResult, RawValue, Relish, RelishBorrow, TypeId, UnknownFields,
+    Value,
+};
 use bytes::Bytes;
 
 /// Parse a value from Relish binary format.
@@ -9,26 +13,112 @@ pub fn parse<T: Relish>(data: Bytes) -> ParseResult<T> {
     parse_tlv::<T>(&mut data)
 }
 
+/// Parse a value from Relish binary format, enforcing the resource limits in
+/// `options`.
+///
+/// Use this rather than [`parse`] for untrusted input.
+pub fn parse_with_options<T: Relish>(data: Bytes, options: &ParseOptions) -> ParseResult<T> {
+    let limits = Limits::new(*options);
+    let mut data = BytesRef::new(&data).with_limits(&limits);
+    parse_tlv::<T>(&mut data)
+}
+
+/// Parse a value from Relish binary format, borrowing from `data`.
+///
+/// Unlike [`parse`], this allows the result to hold `&'de str`, `&'de [u8]`
+/// and other [`RelishBorrow`] types that point directly into the input.
+pub fn parse_borrowed<'de, T: RelishBorrow<'de>>(data: &'de [u8]) -> ParseResult<T> {
+    let mut data = BytesRef::from_slice(data);
+    parse_borrowed_tlv::<T>(&mut data)
+}
+
 #[doc(hidden)]
 pub fn parse_tlv<T: Relish>(data: &mut BytesRef) -> ParseResult<T> {
-    let type_id = TypeId::read_for_type::<T>(data)?;
-    let result = parse_value_for_typeid(data, type_id)?;
+    let result = parse_type_and_value(data)?;
+    check_empty(data)?;
+    Ok(result)
+}
+
+#[doc(hidden)]
+pub fn parse_borrowed_tlv<'de, T: RelishBorrow<'de>>(data: &mut BytesRef<'de>) -> ParseResult<T> {
+    let result = parse_borrowed_type_and_value(data)?;
+    check_empty(data)?;
+    Ok(result)
+}
 
+pub(crate) fn check_empty(data: &BytesRef) -> ParseResult<()> {
     if !data.is_empty() {
         return Err(ParseError::new(ParseErrorKind::ExtraData {
             bytes_remaining: data.len(),
-        }));
+        })
+        .with_offset(data.offset()));
     }
+    Ok(())
+}
 
-    Ok(result)
+/// Fails with [`ParseErrorKind::ExtraData`] if elements made up of values of
+/// `types` take up no space, since they could never consume the remaining
+/// `data`.
+pub(crate) fn check_not_zero_size(data: &BytesRef, types: &[TypeId]) -> ParseResult<()> {
+    if types
+        .iter()
+        .all(|type_id| matches!(type_id.length(), TypeLength::Fixed(0)))
+    {
+        return Err(ParseError::new(ParseErrorKind::ExtraData {
+            bytes_remaining: data.len(),
+        })
+        .with_offset(data.offset()));
+    }
+    Ok(())
 }
 
+/// Parses a type ID followed by a value of that type. Errors that no nested
+/// value has claimed are reported at the offset of the type ID.
+pub(crate) fn parse_type_and_value<T: Relish>(data: &mut BytesRef) -> ParseResult<T> {
+    let offset = data.offset();
+    TypeId::read_for_type::<T>(data)
+        .and_then(|type_id| {
+            let mut value_data = read_value_for_typeid(data, type_id)?;
+            T::parse_typed_value(type_id, &mut value_data)
+        })
+        .map_err(|e| e.or_offset(offset))
+}
+
+/// Like [`parse_type_and_value`], borrowing from the input.
+fn parse_borrowed_type_and_value<'de, T: RelishBorrow<'de>>(
+    data: &mut BytesRef<'de>,
+) -> ParseResult<T> {
+    let offset = data.offset();
+    TypeId::read_matching(data, T::TYPE, T::accepts_type)
+        .and_then(|type_id| {
+            let mut value_data = read_value_for_typeid(data, type_id)?;
+            T::parse_borrowed_typed_value(type_id, &mut value_data)
+        })
+        .map_err(|e| e.or_offset(offset))
+}
+
+/// Parses a value whose type ID has already been read, such as an array
+/// element. Errors that no nested value has claimed are reported at the
+/// offset of the value.
 pub(crate) fn parse_value_for_typeid<T: Relish>(
     data: &mut BytesRef,
     type_id: TypeId,
 ) -> ParseResult<T> {
-    let mut value_data = read_value_for_typeid(data, type_id)?;
-    T::parse_value(&mut value_data)
+    let offset = data.offset();
+    read_value_for_typeid(data, type_id)
+        .and_then(|mut value_data| T::parse_typed_value(type_id, &mut value_data))
+        .map_err(|e| e.or_offset(offset))
+}
+
+/// Reads a type ID of any type and the value that follows it, without parsing
+/// the value. Errors are reported at the offset of the type ID.
+pub(crate) fn read_type_and_value<'a>(
+    data: &mut BytesRef<'a>,
+) -> ParseResult<(TypeId, BytesRef<'a>)> {
+    let offset = data.offset();
+    TypeId::read_for_type::<Value>(data)
+        .and_then(|type_id| Ok((type_id, read_value_for_typeid(data, type_id)?)))
+        .map_err(|e| e.or_offset(offset))
 }
 
 #[doc(hidden)]
@@ -40,7 +130,7 @@ pub fn read_value_for_typeid<'a>(
         TypeLength::Fixed(size) => data.read(size),
         TypeLength::Varsize => {
             let length = read_tagged_varint_length(data)? as usize;
-            data.read(length)
+            data.read_varsize(type_id, length)
         }
     }
 }
@@ -51,6 +141,17 @@ pub fn read_byte(data: &mut BytesRef) -> ParseResult<u8> {
     Ok(bytes[0])
 }
 
+/// Reads a struct field ID or enum variant ID, failing with
+/// [`ParseErrorKind::InvalidFieldId`] if its top bit is set.
+pub(crate) fn read_field_id(data: &mut BytesRef) -> ParseResult<u8> {
+    let offset = data.offset();
+    let field_id = read_byte(data)?;
+    if field_id & 0x80 != 0 {
+        return Err(ParseError::new(ParseErrorKind::InvalidFieldId(field_id)).with_offset(offset));
+    }
+    Ok(field_id)
+}
+
 pub(crate) fn read_tagged_varint_length(data: &mut BytesRef) -> ParseResult<u32> {
     let first_byte = read_byte(data)?;
 
@@ -68,21 +169,63 @@ pub(crate) fn read_tagged_varint_length(data: &mut BytesRef) -> ParseResult<u32>
     }
 }
 
-#[doc(hidden)]
-pub struct StructParser<'a, 'b> {
+/// Reads the fields of a struct, for implementing
+/// [`Relish::parse_value`](crate::Relish::parse_value) by hand.
+///
+/// Fields are looked up by ID, and must be read in increasing ID order:
+/// fields before the one requested are skipped, and a field whose ID is
+/// lower than one already read is reported as absent. Field IDs are checked
+/// as they are passed over, so the struct is rejected if they are not
+/// strictly increasing. Call [`finish`](Self::finish) once every field has
+/// been read to check the fields that remain.
+///
+/// ```
+/// use relish::{BytesRef, ParseError, ParseErrorKind, ParseResult, StructReader};
+///
+/// struct Point {
+///     x: i32,
+///     label: Option<String>,
+/// }
+///
+/// fn parse_point(data: &mut BytesRef) -> ParseResult<Point> {
+///     let mut reader = StructReader::new(data);
+///     let x = reader
+///         .field(0)?
+///         .ok_or_else(|| ParseError::new(ParseErrorKind::MissingRequiredField))?;
+///     let label = reader.field(1)?;
+///     reader.finish()?;
+///     Ok(Point { x, label })
+/// }
+/// ```
+pub struct StructReader<'a, 'b> {
     data: &'a mut BytesRef<'b>,
     last_seen_field_id: Option<u8>,
+    unknown_fields: Option<UnknownFields>,
 }
 
-impl<'a, 'b> StructParser<'a, 'b> {
-    #[doc(hidden)]
+impl<'a, 'b> StructReader<'a, 'b> {
+    /// Creates a reader over the content of a struct.
     pub fn new(data: &'a mut BytesRef<'b>) -> Self {
-        StructParser {
+        StructReader {
             data,
             last_seen_field_id: None,
+            unknown_fields: None,
         }
     }
 
+    /// Keeps the fields that are skipped, rather than discarding them.
+    #[doc(hidden)]
+    pub fn collect_unknown_fields(mut self) -> Self {
+        self.unknown_fields = Some(UnknownFields::new());
+        self
+    }
+
+    /// Returns the fields that were skipped, if they were being collected.
+    #[doc(hidden)]
+    pub fn into_unknown_fields(self) -> UnknownFields {
+        self.unknown_fields.unwrap_or_default()
+    }
+
     fn peek_field_id(&self) -> ParseResult<Option<u8>> {
         if self.data.is_empty() {
             return Ok(None);
@@ -90,35 +233,50 @@ impl<'a, 'b> StructParser<'a, 'b> {
 
         let field_id = self.data[0];
         if field_id & 0x80 != 0 {
-            return Err(ParseError::new(ParseErrorKind::InvalidFieldId(field_id)));
+            return Err(ParseError::new(ParseErrorKind::InvalidFieldId(field_id))
+                .with_offset(self.data.offset()));
         }
 
         Ok(Some(field_id))
     }
 
     fn skip_current_field(&mut self) -> ParseResult<()> {
-        let _field_id = read_byte(self.data)?;
-        let type_byte = read_byte(self.data)?;
-        let type_id = TypeId::from_byte(type_byte)
-            .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidTypeId(type_byte)))?;
-        let _ = read_value_for_typeid(self.data, type_id)?;
+        let field_id = read_byte(self.data)?;
+        let offset = self.data.offset();
+        let (type_id, mut value_data) = read_byte(self.data)
+            .and_then(|type_byte| {
+                let type_id = TypeId::from_byte(type_byte)
+                    .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidTypeId(type_byte)))?;
+                Ok((type_id, read_value_for_typeid(self.data, type_id)?))
+            })
+            .map_err(|e| e.or_offset(offset))?;
+        if let Some(unknown_fields) = &mut self.unknown_fields {
+            unknown_fields.push(
+                field_id,
+                RawValue::parse_typed_value(type_id, &mut value_data)?,
+            );
+        }
         Ok(())
     }
 
-    #[doc(hidden)]
-    pub fn read_value_for_field_id<T: crate::FieldValue>(
-        &mut self,
-        target_field_id: u8,
-    ) -> ParseResult<Option<T::T>> {
+    fn check_field_order(&self, field_id: u8) -> ParseResult<()> {
+        if let Some(last_id) = self.last_seen_field_id
+            && field_id <= last_id
+        {
+            return Err(ParseError::new(ParseErrorKind::FieldOrderViolation {
+                previous: last_id,
+                current: field_id,
+            })
+            .with_offset(self.data.offset()));
+        }
+        Ok(())
+    }
+
+    /// Skips fields before `target_field_id`, and consumes its field ID if
+    /// it is present. Returns whether the field was found.
+    fn seek_field(&mut self, target_field_id: u8) -> ParseResult<bool> {
         while let Some(field_id) = self.peek_field_id()? {
-            if let Some(last_id) = self.last_seen_field_id
-                && field_id <= last_id
-            {
-                return Err(ParseError::new(ParseErrorKind::FieldOrderViolation {
-                    previous: last_id,
-                    current: field_id,
-                }));
-            }
+            self.check_field_order(field_id)?;
 
             if field_id < target_field_id {
                 self.last_seen_field_id = Some(field_id);
@@ -126,28 +284,68 @@ impl<'a, 'b> StructParser<'a, 'b> {
             } else if field_id == target_field_id {
                 self.last_seen_field_id = Some(field_id);
                 let _field_id = read_byte(self.data)?;
-                let type_id = TypeId::read_for_type::<T::T>(self.data)?;
-                let mut value_data = read_value_for_typeid(self.data, type_id)?;
-                return Ok(Some(T::T::parse_value(&mut value_data)?));
+                return Ok(true);
             } else {
-                return Ok(None);
+                return Ok(false);
             }
         }
 
-        Ok(None)
+        Ok(false)
+    }
+
+    /// Reads the field with `field_id`, skipping the fields before it.
+    /// Returns `Ok(None)` if the struct has no such field.
+    pub fn field<T: Relish>(&mut self, field_id: u8) -> ParseResult<Option<T>> {
+        if !self.seek_field(field_id)? {
+            return Ok(None);
+        }
+
+        Ok(Some(parse_type_and_value(self.data)?))
+    }
+
+    /// Like [`field`](Self::field), borrowing from the input.
+    pub fn borrowed_field<T: RelishBorrow<'b>>(&mut self, field_id: u8) -> ParseResult<Option<T>> {
+        if !self.seek_field(field_id)? {
+            return Ok(None);
+        }
+
+        Ok(Some(parse_borrowed_type_and_value(self.data)?))
     }
 
     #[doc(hidden)]
+    pub fn read_value_for_field_id<T: crate::FieldValue>(
+        &mut self,
+        target_field_id: u8,
+    ) -> ParseResult<Option<T::T>> {
+        self.field(target_field_id)
+    }
+
+    #[doc(hidden)]
+    pub fn read_borrowed_value_for_field_id<T: crate::BorrowedFieldValue<'b>>(
+        &mut self,
+        target_field_id: u8,
+    ) -> ParseResult<Option<T::T>> {
+        self.borrowed_field(target_field_id)
+    }
+
+    /// Skips the fields before `target_field_id`, and returns the data
+    /// positioned at the field's type ID if it is present.
+    pub(crate) fn seek_raw_field(
+        &mut self,
+        target_field_id: u8,
+    ) -> ParseResult<Option<&mut BytesRef<'b>>> {
+        if !self.seek_field(target_field_id)? {
+            return Ok(None);
+        }
+
+        Ok(Some(&mut *self.data))
+    }
+
+    /// Skips the remaining fields, checking that their IDs are valid and in
+    /// order.
     pub fn finish(&mut self) -> ParseResult<()> {
         while let Some(field_id) = self.peek_field_id()? {
-            if let Some(last_id) = self.last_seen_field_id
-                && field_id <= last_id
-            {
-                return Err(ParseError::new(ParseErrorKind::FieldOrderViolation {
-                    previous: last_id,
-                    current: field_id,
-                }));
-            }
+            self.check_field_order(field_id)?;
             self.skip_current_field()?;
         }
         Ok(())
@@ -156,7 +354,41 @@ impl<'a, 'b> StructParser<'a, 'b> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{ParseError, ParseErrorKind, parse};
+    use super::{StructReader, read_byte, read_value_for_typeid};
+    use crate::buf::BytesRef;
+    use crate::{ParseError, ParseErrorKind, PathFrame, TypeId, parse};
+
+    #[test]
+    fn test_error_offset() {
+        // The second string in the array has invalid UTF-8
+        let data = &[0x0Fu8, 0x0A, 0x0E, 0x02, b'a', 0x02, 0xFF];
+        let err = parse::<Vec<String>>(bytes::Bytes::from(data.to_vec())).unwrap_err();
+        assert_eq!(err.offset(), Some(5));
+        assert_eq!(err.path().collect::<Vec<_>>(), [&PathFrame::Index(1)]);
+        assert_eq!(
+            err.to_string(),
+            "parse error at offset 5: [1]: invalid UTF-8 in string"
+        );
+
+        let err = ParseError::new(ParseErrorKind::InvalidUtf8);
+        assert_eq!(err.offset(), None);
+        assert_eq!(err.to_string(), "parse error: invalid UTF-8 in string");
+    }
+
+    #[test]
+    fn test_struct_reader() {
+        let data = bytes::Bytes::from(crate::to_vec(&(1u8, "a".to_string(), 3u32)).unwrap());
+        let mut data = BytesRef::new(&data);
+        read_byte(&mut data).unwrap();
+        let mut content = read_value_for_typeid(&mut data, TypeId::Struct).unwrap();
+
+        let mut reader = StructReader::new(&mut content);
+        assert_eq!(reader.field::<u32>(2), Ok(Some(3)));
+        // Fields before the last one read are gone
+        assert_eq!(reader.field::<u8>(0), Ok(None));
+        assert_eq!(reader.field::<u8>(5), Ok(None));
+        assert_eq!(reader.finish(), Ok(()));
+    }
 
     #[test]
     fn test_extra_data() {
@@ -165,9 +397,7 @@ mod tests {
         let result = parse::<u32>(bytes::Bytes::from(data.to_vec()));
         assert_eq!(
             result,
-            Err(ParseError::new(ParseErrorKind::ExtraData {
-                bytes_remaining: 2
-            }))
+            Err(ParseError::new(ParseErrorKind::ExtraData { bytes_remaining: 2 }).with_offset(5))
         );
     }
 }
@@ -0,0 +1,422 @@
+use alloc::vec::Vec;
+
+use bytes::Bytes;
+
+use crate::buf::BytesRef;
+use crate::parse::{
+    check_empty, check_not_zero_size, read_field_id, read_type_and_value, read_value_for_typeid,
+};
+use crate::{ParseError, ParseErrorKind, ParseResult, TypeId, Value};
+
+/// An event produced by a [`Reader`].
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// The start of a struct. It is followed by a [`Field`](Event::Field) and
+    /// a value for each field, then [`End`](Event::End).
+    StructStart,
+    /// A struct field ID. The field's value follows.
+    Field(u8),
+    /// The start of an enum. It is followed by a [`Variant`](Event::Variant)
+    /// and the variant's value, then [`End`](Event::End).
+    EnumStart,
+    /// An enum variant ID. The variant's value follows.
+    Variant(u8),
+    /// The start of an array with elements of the given type. It is followed
+    /// by each element, then [`End`](Event::End).
+    ArrayStart(TypeId),
+    /// The start of a map with keys and values of the given types. It is
+    /// followed by each key and then its value, then [`End`](Event::End).
+    MapStart(TypeId, TypeId),
+    /// A value that is not a container, with its unparsed content. Decode it
+    /// with [`Relish::parse_value`](crate::Relish::parse_value) for a type
+    /// with the same type ID.
+    Scalar(TypeId, BytesRef<'a>),
+    /// The end of the innermost open struct, enum, array or map.
+    End,
+}
+
+#[derive(Clone, Copy)]
+enum EnumStep {
+    Variant,
+    Value,
+    End,
+}
+
+/// What a [`Reader`] found when reading as far as its next event.
+enum Step<'a> {
+    /// The whole message has been read.
+    Done,
+    /// An event that does not start a value.
+    Event(Event<'a>),
+    /// A value's type ID, unparsed content and offset.
+    Value(TypeId, BytesRef<'a>, usize),
+}
+
+/// A container that the reader is partway through, with its remaining
+/// content.
+enum Frame<'a> {
+    Struct {
+        data: BytesRef<'a>,
+        last_field_id: Option<u8>,
+        in_field: bool,
+    },
+    Enum {
+        data: BytesRef<'a>,
+        step: EnumStep,
+    },
+    Array {
+        data: BytesRef<'a>,
+        element_type: TypeId,
+    },
+    Map {
+        data: BytesRef<'a>,
+        key_type: TypeId,
+        value_type: TypeId,
+        at_value: bool,
+    },
+}
+
+/// A pull parser that walks a single message as a sequence of [`Event`]s,
+/// without needing to know its type.
+///
+/// Each call to [`next_event`](Self::next_event) reads only as far as the
+/// next event, and [`skip`](Self::skip) passes over the rest of a container
+/// using its length prefix. Struct field IDs, enum variant IDs and type IDs
+/// are checked as they are read, but scalar contents are left to the caller
+/// and map keys are not checked for duplicates. Once an error has been
+/// returned, no more events are produced.
+///
+/// ```
+/// use bytes::Bytes;
+/// use relish::{Event, Reader};
+///
+/// let data = Bytes::from(relish::to_vec(&vec![1u32, 2]).unwrap());
+/// let mut reader = Reader::new(&data);
+/// assert!(matches!(reader.next_event(), Ok(Some(Event::ArrayStart(relish::TypeId::U32)))));
+/// assert!(matches!(reader.next_event(), Ok(Some(Event::Scalar(..)))));
+/// reader.skip().unwrap();
+/// assert!(matches!(reader.next_event(), Ok(None)));
+/// ```
+pub struct Reader<'a> {
+    data: BytesRef<'a>,
+    stack: Vec<Frame<'a>>,
+    started: bool,
+    failed: bool,
+}
+
+impl<'a> Reader<'a> {
+    /// Creates a reader over the message in `data`. Scalars are returned as
+    /// zero-copy slices of `data`.
+    pub fn new(data: &'a Bytes) -> Self {
+        Reader::from_bytes_ref(BytesRef::new(data))
+    }
+
+    /// Creates a reader over the message in a plain slice.
+    pub fn from_slice(data: &'a [u8]) -> Self {
+        Reader::from_bytes_ref(BytesRef::from_slice(data))
+    }
+
+    fn from_bytes_ref(data: BytesRef<'a>) -> Self {
+        Reader {
+            data,
+            stack: Vec::new(),
+            started: false,
+            failed: false,
+        }
+    }
+
+    /// Returns the number of containers that have been started but not
+    /// ended.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Reads the next event, or returns `Ok(None)` once the whole message has
+    /// been read.
+    pub fn next_event(&mut self) -> ParseResult<Option<Event<'a>>> {
+        if self.failed {
+            return Ok(None);
+        }
+        let result = self.read_event();
+        self.failed = result.is_err();
+        result
+    }
+
+    /// Skips the rest of the innermost open container, including its
+    /// [`End`](Event::End). Called after a start event, this skips the whole
+    /// container; called before the first event, it skips the whole message.
+    pub fn skip(&mut self) -> ParseResult<()> {
+        if self.stack.pop().is_some() || self.started || self.failed {
+            return Ok(());
+        }
+        self.started = true;
+        let result = read_type_and_value(&mut self.data).and_then(|_| check_empty(&self.data));
+        self.failed = result.is_err();
+        result
+    }
+
+    fn read_event(&mut self) -> ParseResult<Option<Event<'a>>> {
+        match self.read_step()? {
+            Step::Done => Ok(None),
+            Step::Event(event) => Ok(Some(event)),
+            Step::Value(type_id, value, offset) => self.enter(type_id, value, offset).map(Some),
+        }
+    }
+
+    /// Reads as far as the next event in the innermost open container.
+    fn read_step(&mut self) -> ParseResult<Step<'a>> {
+        let Some(frame) = self.stack.last_mut() else {
+            if self.started {
+                return Ok(Step::Done);
+            }
+            self.started = true;
+            let (type_id, value) = read_type_and_value(&mut self.data)?;
+            check_empty(&self.data)?;
+            return Ok(Step::Value(type_id, value, 0));
+        };
+
+        match frame {
+            Frame::Struct {
+                data,
+                last_field_id,
+                in_field,
+            } => {
+                if *in_field {
+                    *in_field = false;
+                    return read_tlv(data);
+                }
+                if data.is_empty() {
+                    self.stack.pop();
+                    return Ok(Step::Event(Event::End));
+                }
+
+                let offset = data.offset();
+                let field_id = read_field_id(data)?;
+                if let Some(previous) = *last_field_id
+                    && field_id <= previous
+                {
+                    return Err(ParseError::new(ParseErrorKind::FieldOrderViolation {
+                        previous,
+                        current: field_id,
+                    })
+                    .with_offset(offset));
+                }
+                *last_field_id = Some(field_id);
+                *in_field = true;
+                Ok(Step::Event(Event::Field(field_id)))
+            }
+            Frame::Enum { data, step } => match *step {
+                EnumStep::Variant => {
+                    *step = EnumStep::Value;
+                    Ok(Step::Event(Event::Variant(read_field_id(data)?)))
+                }
+                EnumStep::Value => {
+                    *step = EnumStep::End;
+                    read_tlv(data)
+                }
+                EnumStep::End => {
+                    check_empty(data)?;
+                    self.stack.pop();
+                    Ok(Step::Event(Event::End))
+                }
+            },
+            Frame::Array { data, element_type } => {
+                if data.is_empty() {
+                    self.stack.pop();
+                    return Ok(Step::Event(Event::End));
+                }
+                check_not_zero_size(data, &[*element_type])?;
+                read_element(data, *element_type)
+            }
+            Frame::Map {
+                data,
+                key_type,
+                value_type,
+                at_value,
+            } => {
+                if data.is_empty() && !*at_value {
+                    self.stack.pop();
+                    return Ok(Step::Event(Event::End));
+                }
+                if !*at_value {
+                    check_not_zero_size(data, &[*key_type, *value_type])?;
+                }
+                let type_id = if *at_value { *value_type } else { *key_type };
+                *at_value = !*at_value;
+                read_element(data, type_id)
+            }
+        }
+    }
+
+    /// Returns the event that starts a value of `type_id` with content
+    /// `data`, opening a frame for it if it is a container.
+    fn enter(
+        &mut self,
+        type_id: TypeId,
+        mut data: BytesRef<'a>,
+        offset: usize,
+    ) -> ParseResult<Event<'a>> {
+        let (frame, event) = match type_id {
+            TypeId::Struct => (
+                Frame::Struct {
+                    data,
+                    last_field_id: None,
+                    in_field: false,
+                },
+                Event::StructStart,
+            ),
+            TypeId::Enum => (
+                Frame::Enum {
+                    data,
+                    step: EnumStep::Variant,
+                },
+                Event::EnumStart,
+            ),
+            TypeId::Array => {
+                let element_type =
+                    TypeId::read_for_type::<Value>(&mut data).map_err(|e| e.or_offset(offset))?;
+                (
+                    Frame::Array { data, element_type },
+                    Event::ArrayStart(element_type),
+                )
+            }
+            TypeId::Map => {
+                let (key_type, value_type) = TypeId::read_for_type::<Value>(&mut data)
+                    .and_then(|key_type| Ok((key_type, TypeId::read_for_type::<Value>(&mut data)?)))
+                    .map_err(|e| e.or_offset(offset))?;
+                (
+                    Frame::Map {
+                        data,
+                        key_type,
+                        value_type,
+                        at_value: false,
+                    },
+                    Event::MapStart(key_type, value_type),
+                )
+            }
+            _ => return Ok(Event::Scalar(type_id, data)),
+        };
+        self.stack.push(frame);
+        Ok(event)
+    }
+}
+
+/// Reads a type ID and value, along with the offset of the type ID.
+fn read_tlv<'a>(data: &mut BytesRef<'a>) -> ParseResult<Step<'a>> {
+    let offset = data.offset();
+    let (type_id, value) = read_type_and_value(data)?;
+    Ok(Step::Value(type_id, value, offset))
+}
+
+/// Reads an array element or map entry of `type_id`, along with its offset.
+fn read_element<'a>(data: &mut BytesRef<'a>, type_id: TypeId) -> ParseResult<Step<'a>> {
+    let offset = data.offset();
+    let value = read_value_for_typeid(data, type_id).map_err(|e| e.or_offset(offset))?;
+    Ok(Step::Value(type_id, value, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fmt::Write;
+
+    use bytes::Bytes;
+
+    use super::{Event, Reader};
+    use crate::{ParseError, ParseErrorKind, Relish, to_vec};
+
+    /// Renders the remaining events, decoding scalars as `u32` or `String`.
+    fn events(reader: &mut Reader) -> String {
+        let mut output = String::new();
+        while let Some(event) = reader.next_event().unwrap() {
+            match event {
+                Event::Scalar(type_id, mut data) => match type_id {
+                    crate::TypeId::U32 => {
+                        write!(output, "{} ", u32::parse_value(&mut data).unwrap()).unwrap()
+                    }
+                    _ => write!(output, "{:?} ", String::parse_value(&mut data).unwrap()).unwrap(),
+                },
+                event => write!(output, "{event:?} ").unwrap(),
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn test_reader() {
+        let map = BTreeMap::from([("a".to_string(), vec![1u32, 2])]);
+        let result: Result<u32, String> = Ok(3);
+        let data = Bytes::from(to_vec(&(map, result, "s".to_string())).unwrap());
+
+        let mut reader = Reader::new(&data);
+        assert_eq!(
+            events(&mut reader),
+            "StructStart Field(0) MapStart(String, Array) \"a\" ArrayStart(U32) 1 2 End End \
+             Field(1) EnumStart Variant(0) 3 End Field(2) \"s\" End "
+        );
+        assert_eq!(reader.depth(), 0);
+        assert!(reader.next_event().unwrap().is_none());
+
+        let mut reader = Reader::from_slice(&data);
+        assert!(matches!(reader.next_event(), Ok(Some(Event::StructStart))));
+        assert!(matches!(reader.next_event(), Ok(Some(Event::Field(0)))));
+        assert!(matches!(reader.next_event(), Ok(Some(Event::MapStart(..)))));
+        assert_eq!(reader.depth(), 2);
+        reader.skip().unwrap();
+        assert_eq!(
+            events(&mut reader),
+            "Field(1) EnumStart Variant(0) 3 End Field(2) \"s\" End "
+        );
+
+        let mut reader = Reader::new(&data);
+        reader.skip().unwrap();
+        assert!(reader.next_event().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        // Field 1 follows field 2, after an invalid string that is never decoded
+        let data =
+            Bytes::from_static(&[0x11, 0x10, 0x02, 0x0E, 0x02, 0xFF, 0x01, 0x02, 0x07, 0x00]);
+        let mut reader = Reader::new(&data);
+        assert!(matches!(reader.next_event(), Ok(Some(Event::StructStart))));
+        assert!(matches!(reader.next_event(), Ok(Some(Event::Field(2)))));
+        assert!(matches!(
+            reader.next_event(),
+            Ok(Some(Event::Scalar(crate::TypeId::String, _)))
+        ));
+        assert_eq!(
+            reader.next_event().unwrap_err(),
+            ParseError::new(ParseErrorKind::FieldOrderViolation {
+                previous: 2,
+                current: 1
+            })
+            .with_offset(6)
+        );
+        assert!(reader.next_event().unwrap().is_none());
+
+        // Zero-size elements can never fill the remaining content
+        let mut reader = Reader::from_slice(&[0x0F, 0x04, 0x00, 0xFF]);
+        assert!(matches!(
+            reader.next_event(),
+            Ok(Some(Event::ArrayStart(crate::TypeId::Null)))
+        ));
+        assert_eq!(
+            reader.next_event().unwrap_err(),
+            ParseError::new(ParseErrorKind::ExtraData { bytes_remaining: 1 }).with_offset(3)
+        );
+        let mut reader = Reader::from_slice(&[0x10, 0x06, 0x00, 0x00, 0xFF]);
+        assert!(matches!(reader.next_event(), Ok(Some(Event::MapStart(..)))));
+        assert!(reader.next_event().is_err());
+
+        let data = Bytes::from_static(&[0x0E, 0x04, b'a']);
+        assert_eq!(
+            Reader::new(&data).next_event().unwrap_err(),
+            ParseError::new(ParseErrorKind::InsufficientData {
+                needed: 2,
+                available: 1
+            })
+            .with_offset(0)
+        );
+    }
+}
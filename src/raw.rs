@@ -0,0 +1,211 @@
+use alloc::boxed::Box;
+
+use bytes::Bytes;
+
+use crate::buf::BytesRef;
+use crate::options::{Limits, ParseOptions};
+use crate::types::TypeLength;
+use crate::write::Sink;
+use crate::{
+    ParseError, ParseErrorKind, ParseResult, Relish, TypeId, WriteError, WriteErrorKind,
+    WriteResult,
+};
+
+/// An encoded value of any type, passed through without being decoded.
+///
+/// Parsing a `RawValue` checks only that the type ID is valid and that the
+/// value fits in the input; its content is kept as a zero-copy [`Bytes`]
+/// slice and written back out verbatim. Like [`Value`](crate::Value), it can
+/// be a struct field, an array element or a map value, but every element of an
+/// array or map must have the same type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawValue {
+    type_id: TypeId,
+    content: Bytes,
+}
+
+impl RawValue {
+    /// Encodes `value` as a `RawValue`.
+    pub fn encode<T: Relish>(value: &T) -> WriteResult<Self> {
+        let data = crate::to_vec(value)?;
+        crate::parse(Bytes::from(data)).map_err(|e| {
+            WriteError::new(WriteErrorKind::InvalidEncoding(Box::new(e.kind().clone())))
+        })
+    }
+
+    /// Decodes the value as `T`.
+    ///
+    /// Offsets in errors are relative to the start of the value's content.
+    pub fn decode<T: Relish>(&self) -> ParseResult<T> {
+        self.decode_from(BytesRef::new(&self.content))
+    }
+
+    /// Decodes the value as `T`, enforcing the resource limits in `options`
+    /// as if the value were a message of its own.
+    ///
+    /// The options a `RawValue` was parsed with are not kept, so use this to
+    /// decode values from untrusted input.
+    pub fn decode_with_options<T: Relish>(&self, options: &ParseOptions) -> ParseResult<T> {
+        let limits = Limits::new(*options);
+        self.decode_from(BytesRef::new(&self.content).with_limits(&limits))
+    }
+
+    fn decode_from<T: Relish>(&self, mut data: BytesRef) -> ParseResult<T> {
+        if !T::accepts_type(self.type_id) {
+            return Err(ParseError::new(ParseErrorKind::TypeMismatch {
+                expected: T::TYPE as u8,
+                actual: self.type_id as u8,
+            }));
+        }
+        // Read varsize content as it was originally, so that it counts
+        // towards the depth and string length limits
+        let mut content = match self.type_id.length() {
+            TypeLength::Fixed(_) => data,
+            TypeLength::Varsize => data.read_varsize(self.type_id, self.content.len())?,
+        };
+        T::parse_typed_value(self.type_id, &mut content)
+    }
+
+    /// Returns the type ID the value was encoded with.
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Returns the encoded content of the value, without its type ID or
+    /// length prefix.
+    pub fn content(&self) -> &Bytes {
+        &self.content
+    }
+}
+
+impl Relish for RawValue {
+    const TYPE: TypeId = TypeId::Null;
+
+    /// `TYPE` is only a placeholder, so a `RawValue` can only be parsed
+    /// through [`parse_typed_value`](Relish::parse_typed_value).
+    fn parse_value(_data: &mut BytesRef) -> ParseResult<Self> {
+        Err(ParseError::new(ParseErrorKind::MissingTypeId))
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        if let TypeLength::Varsize = self.type_id.length() {
+            crate::write_tagged_varint_length(buffer, self.content.len())?;
+        }
+        buffer.write_bytes(&self.content)
+    }
+
+    fn value_length(&self) -> usize {
+        let len = self.content.len();
+        match self.type_id.length() {
+            TypeLength::Fixed(_) => len,
+            TypeLength::Varsize => crate::tagged_varint_length_size(len) + len,
+        }
+    }
+
+    fn value_type(&self) -> TypeId {
+        self.type_id
+    }
+
+    fn accepts_type(_type_id: TypeId) -> bool {
+        true
+    }
+
+    fn parse_typed_value(type_id: TypeId, data: &mut BytesRef) -> ParseResult<Self> {
+        let content = data.to_bytes();
+        data.read(data.len())?;
+        Ok(RawValue { type_id, content })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+
+    use super::RawValue;
+    use crate::buf::BytesRef;
+    use crate::{
+        Limit, ParseError, ParseErrorKind, ParseOptions, Relish, TypeId, WriteErrorKind, parse,
+        to_vec,
+    };
+
+    #[test]
+    fn test_raw_value_roundtrip() {
+        // The content of the string is not checked
+        let data = [0x11u8, 0x0E, 0x00, 0x02, 0x07, 0x01, 0x0E, 0x02, 0xFF];
+        let (n, raw): (u8, RawValue) = parse(Bytes::from(data.to_vec())).unwrap();
+        assert_eq!(n, 7);
+        assert_eq!(raw.type_id(), TypeId::String);
+        assert_eq!(raw.content().as_ref(), &[0xFF]);
+        assert_eq!(to_vec(&(n, raw.clone())).unwrap(), data);
+        assert_eq!(
+            raw.decode::<String>(),
+            Err(ParseError::new(ParseErrorKind::InvalidUtf8))
+        );
+
+        // Framing is still checked
+        assert_eq!(
+            parse::<RawValue>(Bytes::from_static(&[0x0E, 0x04, b'a'])),
+            Err(ParseError::new(ParseErrorKind::InsufficientData {
+                needed: 2,
+                available: 1
+            })
+            .with_offset(0))
+        );
+
+        // Without a type ID the length prefix could not be written back
+        let data = Bytes::from_static(&[0x02, b'a']);
+        assert_eq!(
+            <RawValue as Relish>::parse_value(&mut BytesRef::new(&data)),
+            Err(ParseError::new(ParseErrorKind::MissingTypeId))
+        );
+    }
+
+    #[test]
+    fn test_raw_value_collections() {
+        let elements = vec![
+            RawValue::encode(&1u32).unwrap(),
+            RawValue::encode(&2u32).unwrap(),
+        ];
+        let data = to_vec(&elements).unwrap();
+        assert_eq!(data, to_vec(&vec![1u32, 2]).unwrap());
+        assert_eq!(parse::<Vec<RawValue>>(Bytes::from(data)), Ok(elements));
+
+        let map = HashMap::from([(1u8, RawValue::encode(&"a".to_string()).unwrap())]);
+        let data = to_vec(&map).unwrap();
+        let parsed = parse::<HashMap<u8, RawValue>>(Bytes::from(data)).unwrap();
+        assert_eq!(parsed[&1].decode::<String>(), Ok("a".to_string()));
+
+        let mixed = vec![
+            RawValue::encode(&1u32).unwrap(),
+            RawValue::encode(&2u8).unwrap(),
+        ];
+        assert_eq!(
+            to_vec(&mixed).unwrap_err().kind(),
+            &WriteErrorKind::MixedElementTypes {
+                expected: TypeId::U32 as u8,
+                actual: TypeId::U8 as u8
+            }
+        );
+    }
+
+    #[test]
+    fn test_raw_value_decode_with_options() {
+        let raw = RawValue::encode(&"hello".to_string()).unwrap();
+        assert_eq!(raw.decode::<String>(), Ok("hello".to_string()));
+        assert_eq!(
+            raw.decode_with_options::<String>(&ParseOptions::new().max_string_length(4))
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::LimitExceeded {
+                limit: Limit::StringLength,
+                max: 4
+            }
+        );
+        assert_eq!(
+            raw.decode_with_options::<String>(&ParseOptions::new().max_string_length(5)),
+            Ok("hello".to_string())
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use std::io::{self, Read};
+
+use bytes::Bytes;
+
+use crate::record::message_type;
+use crate::types::TypeLength;
+use crate::{ParseError, ParseErrorKind, ParseResult, Relish, parse};
+
+/// The default maximum message size accepted by [`from_reader`] and
+/// [`StreamReader`]: 64 MiB.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Reads a single value in Relish binary format from `reader`.
+///
+/// Exactly the bytes of one value are consumed, so the reader is left
+/// positioned at whatever follows. Messages larger than
+/// [`DEFAULT_MAX_MESSAGE_SIZE`] are rejected before any content is read; use
+/// [`StreamReader`] to configure the limit or to read a sequence of values.
+pub fn from_reader<T: Relish, R: Read>(reader: R) -> ParseResult<T> {
+    StreamReader::new(reader).read()?.ok_or_else(|| {
+        ParseError::new(ParseErrorKind::InsufficientData {
+            needed: 1,
+            available: 0,
+        })
+    })
+}
+
+/// Reads back-to-back top-level Relish values from an [`io::Read`].
+///
+/// Each value's type ID and length prefix are read first, and then exactly
+/// the bytes of its content, so no more than one message is held in memory at
+/// a time.
+pub struct StreamReader<R> {
+    reader: R,
+    max_message_size: usize,
+}
+
+impl<R: Read> StreamReader<R> {
+    /// Creates a reader with the default maximum message size.
+    pub fn new(reader: R) -> Self {
+        StreamReader {
+            reader,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        }
+    }
+
+    /// Sets the maximum size in bytes of a single message, including its type
+    /// ID and length prefix.
+    pub fn max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Reads the next value.
+    ///
+    /// Returns `Ok(None)` if the reader is at end of input before the first
+    /// byte of a value. Input that ends partway through a value is reported as
+    /// [`ParseErrorKind::InsufficientData`].
+    pub fn read<T: Relish>(&mut self) -> ParseResult<Option<T>> {
+        let Some(message) = self.read_message::<T>()? else {
+            return Ok(None);
+        };
+        parse(message).map(Some)
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads the complete encoding of the next value, checking that `T`
+    /// accepts its type ID before reading its content. Use
+    /// [`Value`](crate::Value) as `T` to accept any type.
+    pub(crate) fn read_message<T: Relish>(&mut self) -> ParseResult<Option<Bytes>> {
+        let mut header = [0u8; 5];
+        if read_up_to(&mut self.reader, &mut header[..1])? == 0 {
+            return Ok(None);
+        }
+
+        let type_id = message_type::<T>(header[0])?;
+
+        let (header_len, content_len) = match type_id.length() {
+            TypeLength::Fixed(size) => (1, size),
+            TypeLength::Varsize => {
+                self.read_exact(&mut header[1..2])?;
+                if header[1] & 0x01 == 0 {
+                    (2, (header[1] >> 1) as usize)
+                } else {
+                    self.read_exact(&mut header[2..5])?;
+                    let tagged = u32::from_le_bytes(header[1..5].try_into().unwrap());
+                    (5, (tagged >> 1) as usize)
+                }
+            }
+        };
+
+        let message_len = header_len + content_len;
+        if message_len > self.max_message_size {
+            return Err(ParseError::new(ParseErrorKind::MessageTooLarge {
+                size: message_len,
+                max: self.max_message_size,
+            }));
+        }
+
+        // Grow the buffer as data actually arrives, rather than trusting the
+        // length prefix with an up-front allocation.
+        let mut message = Vec::with_capacity(header_len);
+        message.extend_from_slice(&header[..header_len]);
+        let read = (&mut self.reader)
+            .take(content_len as u64)
+            .read_to_end(&mut message)
+            .map_err(io_error)?;
+        if read < content_len {
+            return Err(ParseError::new(ParseErrorKind::InsufficientData {
+                needed: content_len,
+                available: read,
+            }));
+        }
+
+        Ok(Some(Bytes::from(message)))
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> ParseResult<()> {
+        let read = read_up_to(&mut self.reader, buf)?;
+        if read < buf.len() {
+            return Err(ParseError::new(ParseErrorKind::InsufficientData {
+                needed: buf.len(),
+                available: read,
+            }));
+        }
+        Ok(())
+    }
+}
+
+/// Reads until `buf` is full or the reader is at end of input, returning the
+/// number of bytes read.
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> ParseResult<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(io_error(e)),
+        }
+    }
+    Ok(filled)
+}
+
+fn io_error(e: io::Error) -> ParseError {
+    ParseError::new(ParseErrorKind::Io(e.kind()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StreamReader, from_reader};
+    use crate::{ParseError, ParseErrorKind, to_vec};
+
+    #[test]
+    fn test_from_reader() {
+        let value = vec!["foo".to_string(), "bar".repeat(100)];
+        let data = to_vec(&value).unwrap();
+        assert_eq!(from_reader::<Vec<String>, _>(&data[..]), Ok(value));
+
+        assert_eq!(
+            from_reader::<u32, _>(&[0x04u8, 0x2A, 0x00, 0x00, 0x00][..]),
+            Ok(42)
+        );
+    }
+
+    #[test]
+    fn test_from_reader_errors() {
+        assert_eq!(
+            from_reader::<u32, _>(&[][..]),
+            Err(ParseError::new(ParseErrorKind::InsufficientData {
+                needed: 1,
+                available: 0
+            }))
+        );
+        assert_eq!(
+            from_reader::<u32, _>(&[0x0E, 0x02, b'a'][..]),
+            Err(ParseError::new(ParseErrorKind::TypeMismatch {
+                expected: 0x04,
+                actual: 0x0E
+            }))
+        );
+        assert_eq!(
+            from_reader::<String, _>(&[0x0E, 0x0A, b'a'][..]),
+            Err(ParseError::new(ParseErrorKind::InsufficientData {
+                needed: 5,
+                available: 1
+            }))
+        );
+        assert_eq!(
+            from_reader::<String, _>(&[0x0E, 0x03, 0x00][..]),
+            Err(ParseError::new(ParseErrorKind::InsufficientData {
+                needed: 3,
+                available: 1
+            }))
+        );
+    }
+
+    #[test]
+    fn test_max_message_size() {
+        // A length prefix claiming ~1 GiB of content.
+        let data = [0x0Fu8, 0xFF, 0xFF, 0xFF, 0x7F, 0x02];
+        let mut reader = StreamReader::new(&data[..]).max_message_size(1024);
+        assert_eq!(
+            reader.read::<Vec<u8>>(),
+            Err(ParseError::new(ParseErrorKind::MessageTooLarge {
+                size: 5 + (u32::MAX >> 2) as usize,
+                max: 1024
+            }))
+        );
+
+        let data = to_vec(&"x".repeat(10)).unwrap();
+        let mut reader = StreamReader::new(&data[..]).max_message_size(12);
+        assert_eq!(reader.read::<String>(), Ok(Some("x".repeat(10))));
+        let mut reader = StreamReader::new(&data[..]).max_message_size(11);
+        assert!(reader.read::<String>().is_err());
+    }
+
+    #[test]
+    fn test_stream_reader_sequence() {
+        let mut data = Vec::new();
+        data.extend(to_vec(&1u32).unwrap());
+        data.extend(to_vec(&"two".to_string()).unwrap());
+        data.extend(to_vec(&vec![3u64]).unwrap());
+
+        let mut reader = StreamReader::new(&data[..]);
+        assert_eq!(reader.read::<u32>(), Ok(Some(1)));
+        assert_eq!(reader.read::<String>(), Ok(Some("two".to_string())));
+        assert_eq!(reader.read::<Vec<u64>>(), Ok(Some(vec![3])));
+        assert_eq!(reader.read::<u32>(), Ok(None));
+    }
+}
@@ -0,0 +1,339 @@
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::io;
+
+use bytes::Bytes;
+
+use crate::types::TypeLength;
+use crate::{ParseError, ParseErrorKind, ParseResult, Relish, TypeId, Value, parse};
+#[cfg(feature = "std")]
+use crate::{RelishBorrow, StreamReader};
+
+/// Returns the type ID of a message starting with `type_byte`, checking that
+/// `T` accepts it.
+pub(crate) fn message_type<T: Relish>(type_byte: u8) -> ParseResult<TypeId> {
+    let type_id = TypeId::from_byte(type_byte)
+        .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidTypeId(type_byte)))?;
+    if !T::accepts_type(type_id) {
+        return Err(ParseError::new(ParseErrorKind::TypeMismatch {
+            expected: T::TYPE as u8,
+            actual: type_byte,
+        }));
+    }
+    Ok(type_id)
+}
+
+/// Returns the length of the message at the start of `src`, or, if its
+/// header is incomplete, the length of the header.
+pub(crate) fn frame_length<T: Relish>(src: &[u8]) -> ParseResult<usize> {
+    let Some(&type_byte) = src.first() else {
+        return Ok(1);
+    };
+    let type_id = message_type::<T>(type_byte)?;
+
+    let (header_len, content_len) = match type_id.length() {
+        TypeLength::Fixed(size) => (1, size),
+        TypeLength::Varsize => {
+            let Some(&first) = src.get(1) else {
+                return Ok(2);
+            };
+            if first & 0x01 == 0 {
+                (2, (first >> 1) as usize)
+            } else {
+                let Some(prefix) = src.get(1..5) else {
+                    return Ok(5);
+                };
+                let tagged = u32::from_le_bytes(prefix.try_into().unwrap());
+                (5, (tagged >> 1) as usize)
+            }
+        }
+    };
+    Ok(header_len + content_len)
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// A source of concatenated records for a [`RecordReader`]: either [`Bytes`]
+/// or, with the `std` feature, a [`StreamReader`].
+pub trait RecordSource: private::Sealed {
+    /// Returns the complete encoding of the next record, or `None` at the end
+    /// of input.
+    #[doc(hidden)]
+    fn next_record(&mut self) -> ParseResult<Option<Bytes>>;
+}
+
+impl private::Sealed for Bytes {}
+
+impl RecordSource for Bytes {
+    fn next_record(&mut self) -> ParseResult<Option<Bytes>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let len = frame_length::<Value>(self)?;
+        if len > self.len() {
+            return Err(ParseError::new(ParseErrorKind::TruncatedRecord));
+        }
+        Ok(Some(self.split_to(len)))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> private::Sealed for StreamReader<R> {}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> RecordSource for StreamReader<R> {
+    fn next_record(&mut self) -> ParseResult<Option<Bytes>> {
+        self.read_message::<Value>().map_err(|e| match e.kind() {
+            ParseErrorKind::InsufficientData { .. } => {
+                ParseError::new(ParseErrorKind::TruncatedRecord)
+            }
+            _ => e,
+        })
+    }
+}
+
+/// Reads top-level values that were written back to back, such as the
+/// records of an append-only log.
+///
+/// Iterating yields the complete encoding of each record, type ID included,
+/// and [`read`](Self::read) and [`records`](Self::records) parse them as `T`.
+/// A record that fails to parse is still consumed, so reading can carry on
+/// with the next one; offsets in its error are relative to the start of the
+/// record, which [`position`](Self::position) reports before it is read.
+/// Input that ends partway through a record fails with
+/// [`ParseErrorKind::TruncatedRecord`], after which nothing more is read.
+///
+/// ```
+/// use bytes::Bytes;
+///
+/// let mut log = Vec::new();
+/// let mut writer = relish::RecordWriter::new(&mut log);
+/// writer.write(&1u32).unwrap();
+/// writer.write(&2u32).unwrap();
+///
+/// let mut reader = relish::RecordReader::new(Bytes::from(log));
+/// let values: Vec<u32> = reader.records().collect::<Result<_, _>>().unwrap();
+/// assert_eq!(values, [1, 2]);
+/// ```
+pub struct RecordReader<S> {
+    source: S,
+    position: usize,
+    done: bool,
+}
+
+impl RecordReader<Bytes> {
+    /// Creates a reader over records held in memory. Records are returned as
+    /// zero-copy slices of `data`.
+    pub fn new(data: Bytes) -> Self {
+        RecordReader::from_source(data)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> RecordReader<StreamReader<R>> {
+    /// Creates a reader over records read from `reader`, using the default
+    /// maximum message size. Wrap a configured [`StreamReader`] with
+    /// [`From`] to change it.
+    pub fn from_reader(reader: R) -> Self {
+        RecordReader::from_source(StreamReader::new(reader))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> From<StreamReader<R>> for RecordReader<StreamReader<R>> {
+    fn from(reader: StreamReader<R>) -> Self {
+        RecordReader::from_source(reader)
+    }
+}
+
+impl<S: RecordSource> RecordReader<S> {
+    fn from_source(source: S) -> Self {
+        RecordReader {
+            source,
+            position: 0,
+            done: false,
+        }
+    }
+
+    /// Returns the number of bytes consumed so far, which is the offset of the
+    /// next record.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Reads the next record and parses it as `T`. Returns `Ok(None)` at the
+    /// end of input.
+    pub fn read<T: Relish>(&mut self) -> ParseResult<Option<T>> {
+        match self.next() {
+            Some(record) => parse(record?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns an iterator that parses each remaining record as `T`.
+    pub fn records<T: Relish>(&mut self) -> Records<'_, S, T> {
+        Records {
+            reader: self,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the underlying source.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: RecordSource> Iterator for RecordReader<S> {
+    type Item = ParseResult<Bytes>;
+
+    fn next(&mut self) -> Option<ParseResult<Bytes>> {
+        if self.done {
+            return None;
+        }
+        match self.source.next_record() {
+            Ok(Some(record)) => {
+                self.position += record.len();
+                Some(Ok(record))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e.with_offset(self.position)))
+            }
+        }
+    }
+}
+
+/// An iterator over the records of a [`RecordReader`], parsed as `T`.
+pub struct Records<'r, S, T> {
+    reader: &'r mut RecordReader<S>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<S: RecordSource, T: Relish> Iterator for Records<'_, S, T> {
+    type Item = ParseResult<T>;
+
+    fn next(&mut self) -> Option<ParseResult<T>> {
+        self.reader.read().transpose()
+    }
+}
+
+/// Appends top-level values to an [`io::Write`], so that they can be read back
+/// with a [`RecordReader`].
+#[cfg(feature = "std")]
+pub struct RecordWriter<W> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> RecordWriter<W> {
+    /// Creates a writer that appends records to `inner`.
+    pub fn new(inner: W) -> Self {
+        RecordWriter {
+            inner,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Appends `value` as a record.
+    ///
+    /// The record is encoded into a reused buffer before any of it is
+    /// written, so a value that fails to encode leaves the output unchanged.
+    pub fn write<'a, T: RelishBorrow<'a>>(&mut self, value: &T) -> crate::WriteResult<()> {
+        self.buffer.clear();
+        self.buffer.push(value.value_type() as u8);
+        value.write_value(&mut self.buffer)?;
+        self.inner.write_all(&self.buffer)?;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> crate::WriteResult<()> {
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::{RecordReader, RecordWriter};
+    use crate::{ParseError, ParseErrorKind, StreamReader, Value};
+
+    fn log() -> Vec<u8> {
+        let mut writer = RecordWriter::new(Vec::new());
+        writer.write(&1u32).unwrap();
+        writer.write(&"two".to_string()).unwrap();
+        writer.write(&3u32).unwrap();
+        writer.into_inner()
+    }
+
+    #[test]
+    fn test_record_reader() {
+        let data = log();
+
+        let mut reader = RecordReader::new(Bytes::from(data.clone()));
+        assert_eq!(reader.read::<u32>(), Ok(Some(1)));
+        assert_eq!(reader.position(), 5);
+        assert_eq!(
+            reader.next(),
+            Some(Ok(Bytes::from_static(&[0x0E, 0x06, b't', b'w', b'o'])))
+        );
+        assert_eq!(reader.read::<u32>(), Ok(Some(3)));
+        assert_eq!(reader.read::<u32>(), Ok(None));
+
+        // A record of the wrong type is skipped over
+        let mut reader = RecordReader::from_reader(&data[..]);
+        let results: Vec<_> = reader.records::<u32>().collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[1].is_err());
+        assert_eq!(results[2], Ok(3));
+
+        let reader = RecordReader::from(StreamReader::new(&data[..]));
+        assert_eq!(reader.count(), 3);
+    }
+
+    #[test]
+    fn test_record_reader_truncated() {
+        let mut data = log();
+        data.truncate(data.len() - 1);
+
+        let mut reader = RecordReader::new(Bytes::from(data.clone()));
+        assert_eq!(reader.records::<Value>().count(), 3);
+        let mut reader = RecordReader::new(Bytes::from(data.clone()));
+        reader.nth(1).unwrap().unwrap();
+        assert_eq!(
+            reader.next(),
+            Some(Err(
+                ParseError::new(ParseErrorKind::TruncatedRecord).with_offset(10)
+            ))
+        );
+        assert_eq!(reader.next(), None);
+
+        let mut reader = RecordReader::from_reader(&data[..]);
+        reader.nth(1).unwrap().unwrap();
+        assert_eq!(
+            reader.read::<u32>(),
+            Err(ParseError::new(ParseErrorKind::TruncatedRecord).with_offset(10))
+        );
+        assert_eq!(reader.read::<u32>(), Ok(None));
+    }
+}
@@ -0,0 +1,1329 @@
+//! A [serde](https://serde.rs) data format for Relish.
+//!
+//! This allows types deriving `serde::Serialize` and `serde::Deserialize` to
+//! be encoded as Relish without `#[relish(field_id = N)]` annotations. The
+//! mapping onto the Relish types is:
+//!
+//! - Primitives map to the matching fixed-size type; `char` and `str` map to
+//!   `String`, and byte slices map to `Array` of `u8`.
+//! - `()`, unit structs and `None` map to `Null`. A `None` struct field is
+//!   omitted, exactly as with `#[derive(Relish)]`.
+//! - Structs map to `Struct`. A field's ID is its name if the name is a number
+//!   (e.g. `#[serde(rename = "3")]`), and its declaration index otherwise.
+//! - Tuples and tuple structs map to `Struct` with field IDs `0..n`.
+//! - Sequences map to `Array`, and maps to `Map`. Relish arrays and maps are
+//!   homogeneous, so serializing elements of differing types fails with
+//!   [`WriteErrorKind::MixedElementTypes`]. Serde gives no element type for
+//!   an empty sequence or map, so they record `Null` as their element types
+//!   and do not parse as the `Vec`, `HashMap` and other collections of
+//!   `#[derive(Relish)]` types, which expect their declared element types.
+//!   Data for those types should be written with [`crate::to_vec`].
+//! - Enum variants map to `Enum`, with variant IDs chosen in the same way as
+//!   struct field IDs. Unit variants hold `Null`, and tuple and struct
+//!   variants hold a `Struct`.
+//!
+//! Newtype structs are transparent.
+
+use std::marker::PhantomData;
+
+use ::serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use ::serde::ser::{self, Serialize};
+
+use crate::buf::BytesRef;
+use crate::parse::{check_empty, check_not_zero_size, read_byte, read_value_for_typeid};
+use crate::types::TypeLength;
+use crate::{
+    ParseError, ParseErrorKind, ParseResult, Relish, TypeId, WriteError, WriteErrorKind,
+    WriteResult, write_tagged_varint_length,
+};
+
+/// Serialize a `serde::Serialize` value to Relish binary format as a Vec<u8>.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> WriteResult<Vec<u8>> {
+    let encoded = value.serialize(ValueSerializer)?;
+
+    let mut buffer = Vec::with_capacity(1 + encoded.data.len());
+    buffer.push(encoded.type_id as u8);
+    buffer.extend_from_slice(&encoded.data);
+    Ok(buffer)
+}
+
+/// Deserialize a `serde::Deserialize` value from Relish binary format.
+///
+/// Strings and byte arrays are borrowed from `data` where the target type
+/// allows it.
+pub fn from_slice<'de, T: de::Deserialize<'de>>(data: &'de [u8]) -> ParseResult<T> {
+    let mut data = BytesRef::from_slice(data);
+    let mut value = ValueDeserializer::read_type_id(&mut data)
+        .and_then(|type_id| ValueDeserializer::read(&mut data, type_id))
+        .map_err(|e| e.or_offset(0))?;
+    value.offset = 0;
+
+    if !data.is_empty() {
+        return Err(ParseError::new(ParseErrorKind::ExtraData {
+            bytes_remaining: data.len(),
+        })
+        .with_offset(data.offset()));
+    }
+
+    value.deserialize_seed(PhantomData)
+}
+
+impl ser::Error for WriteError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        WriteError::new(WriteErrorKind::Custom(msg.to_string()))
+    }
+}
+
+impl de::Error for ParseError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        ParseError::new(ParseErrorKind::Custom(msg.to_string()))
+    }
+}
+
+/// Returns the field or variant ID for a serde name: the name itself if it is
+/// numeric, and otherwise its declaration index.
+fn field_id(name: &str, index: usize) -> WriteResult<u8> {
+    let id = numeric_id(name).unwrap_or_else(|| u8::try_from(index).unwrap_or(u8::MAX));
+    if id & 0x80 != 0 {
+        return Err(WriteError::new(WriteErrorKind::FieldIdTooLarge(id)));
+    }
+    Ok(id)
+}
+
+/// Returns the serde name for a field or variant ID, the inverse of
+/// [`field_id`].
+fn field_name(names: &'static [&'static str], id: u8) -> ParseResult<Option<&'static str>> {
+    if let Some(invalid) = names
+        .iter()
+        .filter_map(|name| numeric_id(name))
+        .find(|name_id| name_id & 0x80 != 0)
+    {
+        return Err(ParseError::new(ParseErrorKind::InvalidFieldId(invalid)));
+    }
+    Ok(names
+        .iter()
+        .copied()
+        .find(|name| numeric_id(name) == Some(id))
+        .or_else(|| {
+            names
+                .get(id as usize)
+                .copied()
+                .filter(|name| numeric_id(name).is_none())
+        }))
+}
+
+/// Returns the ID given by a numeric serde name, saturating at `u8::MAX` so
+/// that out of range names are rejected rather than treated as non-numeric.
+fn numeric_id(name: &str) -> Option<u8> {
+    name.parse::<i128>()
+        .ok()
+        .map(|id| u8::try_from(id).unwrap_or(u8::MAX))
+}
+
+/// A serialized value: its type ID and everything after it.
+struct Encoded {
+    type_id: TypeId,
+    data: Vec<u8>,
+    is_none: bool,
+}
+
+impl Encoded {
+    fn fixed<T: Relish>(value: T) -> WriteResult<Self> {
+        let mut data = Vec::with_capacity(value.value_length());
+        value.write_value(&mut data)?;
+        Ok(Encoded {
+            type_id: T::TYPE,
+            data,
+            is_none: false,
+        })
+    }
+
+    fn varsize(type_id: TypeId, content: &[u8]) -> WriteResult<Self> {
+        let mut data = Vec::with_capacity(4 + content.len());
+        write_tagged_varint_length(&mut data, content.len())?;
+        data.extend_from_slice(content);
+        Ok(Encoded {
+            type_id,
+            data,
+            is_none: false,
+        })
+    }
+
+    fn null() -> Self {
+        Encoded {
+            type_id: TypeId::Null,
+            data: Vec::new(),
+            is_none: false,
+        }
+    }
+
+    fn enum_variant(variant_id: u8, value: Encoded) -> WriteResult<Self> {
+        let mut content = Vec::with_capacity(2 + value.data.len());
+        content.push(variant_id);
+        content.push(value.type_id as u8);
+        content.extend_from_slice(&value.data);
+        Encoded::varsize(TypeId::Enum, &content)
+    }
+}
+
+/// Checks that `actual` matches the element type recorded so far, recording
+/// it if this is the first element.
+fn check_element_type(expected: &mut Option<TypeId>, actual: TypeId) -> WriteResult<()> {
+    match *expected {
+        None => {
+            *expected = Some(actual);
+            Ok(())
+        }
+        Some(expected) if expected == actual => Ok(()),
+        Some(expected) => Err(WriteError::new(WriteErrorKind::MixedElementTypes {
+            expected: expected as u8,
+            actual: actual as u8,
+        })),
+    }
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Encoded;
+    type Error = WriteError;
+
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = StructSerializer;
+    type SerializeTupleStruct = StructSerializer;
+    type SerializeTupleVariant = StructSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = StructSerializer;
+
+    fn serialize_bool(self, v: bool) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> WriteResult<Encoded> {
+        Encoded::fixed(v)
+    }
+
+    fn serialize_char(self, v: char) -> WriteResult<Encoded> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> WriteResult<Encoded> {
+        Encoded::varsize(TypeId::String, v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> WriteResult<Encoded> {
+        let mut content = Vec::with_capacity(1 + v.len());
+        content.push(TypeId::U8 as u8);
+        content.extend_from_slice(v);
+        Encoded::varsize(TypeId::Array, &content)
+    }
+
+    fn serialize_none(self) -> WriteResult<Encoded> {
+        Ok(Encoded {
+            is_none: true,
+            ..Encoded::null()
+        })
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> WriteResult<Encoded> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> WriteResult<Encoded> {
+        Ok(Encoded::null())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> WriteResult<Encoded> {
+        Ok(Encoded::null())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> WriteResult<Encoded> {
+        let variant_id = field_id(variant, variant_index as usize)?;
+        Encoded::enum_variant(variant_id, Encoded::null())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> WriteResult<Encoded> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> WriteResult<Encoded> {
+        let variant_id = field_id(variant, variant_index as usize)?;
+        Encoded::enum_variant(variant_id, value.serialize(ValueSerializer)?)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> WriteResult<SeqSerializer> {
+        let mut content = Vec::with_capacity(1 + len.unwrap_or(0));
+        content.push(TypeId::Null as u8);
+        Ok(SeqSerializer {
+            elem_type: None,
+            content,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> WriteResult<StructSerializer> {
+        Ok(StructSerializer::new(None, len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> WriteResult<StructSerializer> {
+        Ok(StructSerializer::new(None, len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> WriteResult<StructSerializer> {
+        let variant_id = field_id(variant, variant_index as usize)?;
+        Ok(StructSerializer::new(Some(variant_id), len))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> WriteResult<MapSerializer> {
+        Ok(MapSerializer {
+            key_type: None,
+            value_type: None,
+            content: vec![TypeId::Null as u8, TypeId::Null as u8],
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> WriteResult<StructSerializer> {
+        Ok(StructSerializer::new(None, len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> WriteResult<StructSerializer> {
+        let variant_id = field_id(variant, variant_index as usize)?;
+        Ok(StructSerializer::new(Some(variant_id), len))
+    }
+}
+
+struct SeqSerializer {
+    elem_type: Option<TypeId>,
+    content: Vec<u8>,
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Encoded;
+    type Error = WriteError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> WriteResult<()> {
+        let encoded = value.serialize(ValueSerializer)?;
+        check_element_type(&mut self.elem_type, encoded.type_id)?;
+        self.content.extend_from_slice(&encoded.data);
+        Ok(())
+    }
+
+    fn end(mut self) -> WriteResult<Encoded> {
+        self.content[0] = self.elem_type.unwrap_or(TypeId::Null) as u8;
+        Encoded::varsize(TypeId::Array, &self.content)
+    }
+}
+
+struct MapSerializer {
+    key_type: Option<TypeId>,
+    value_type: Option<TypeId>,
+    content: Vec<u8>,
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Encoded;
+    type Error = WriteError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> WriteResult<()> {
+        let encoded = key.serialize(ValueSerializer)?;
+        check_element_type(&mut self.key_type, encoded.type_id)?;
+        self.content.extend_from_slice(&encoded.data);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> WriteResult<()> {
+        let encoded = value.serialize(ValueSerializer)?;
+        check_element_type(&mut self.value_type, encoded.type_id)?;
+        self.content.extend_from_slice(&encoded.data);
+        Ok(())
+    }
+
+    fn end(mut self) -> WriteResult<Encoded> {
+        self.content[0] = self.key_type.unwrap_or(TypeId::Null) as u8;
+        self.content[1] = self.value_type.unwrap_or(TypeId::Null) as u8;
+        Encoded::varsize(TypeId::Map, &self.content)
+    }
+}
+
+/// Serializes structs and tuples (optionally as an enum variant) by
+/// collecting their fields, which are sorted by ID when finished.
+struct StructSerializer {
+    variant_id: Option<u8>,
+    next_index: usize,
+    fields: Vec<(u8, Encoded)>,
+}
+
+impl StructSerializer {
+    fn new(variant_id: Option<u8>, len: usize) -> Self {
+        StructSerializer {
+            variant_id,
+            next_index: 0,
+            fields: Vec::with_capacity(len),
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, name: &str, value: &T) -> WriteResult<()> {
+        let id = field_id(name, self.next_index)?;
+        self.next_index += 1;
+
+        let encoded = value.serialize(ValueSerializer)?;
+        if !encoded.is_none {
+            self.fields.push((id, encoded));
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> WriteResult<Encoded> {
+        self.fields.sort_by_key(|(id, _)| *id);
+
+        let mut content = Vec::new();
+        for (i, (id, value)) in self.fields.iter().enumerate() {
+            if i > 0 && self.fields[i - 1].0 == *id {
+                return Err(WriteError::new(WriteErrorKind::DuplicateFieldId(*id)));
+            }
+            content.push(*id);
+            content.push(value.type_id as u8);
+            content.extend_from_slice(&value.data);
+        }
+
+        let encoded = Encoded::varsize(TypeId::Struct, &content)?;
+        match self.variant_id {
+            Some(variant_id) => Encoded::enum_variant(variant_id, encoded),
+            None => Ok(encoded),
+        }
+    }
+}
+
+impl ser::SerializeTuple for StructSerializer {
+    type Ok = Encoded;
+    type Error = WriteError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> WriteResult<()> {
+        self.push("", value)
+    }
+
+    fn end(self) -> WriteResult<Encoded> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for StructSerializer {
+    type Ok = Encoded;
+    type Error = WriteError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> WriteResult<()> {
+        self.push("", value)
+    }
+
+    fn end(self) -> WriteResult<Encoded> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for StructSerializer {
+    type Ok = Encoded;
+    type Error = WriteError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> WriteResult<()> {
+        self.push("", value)
+    }
+
+    fn end(self) -> WriteResult<Encoded> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Encoded;
+    type Error = WriteError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> WriteResult<()> {
+        self.push(key, value)
+    }
+
+    fn skip_field(&mut self, _key: &'static str) -> WriteResult<()> {
+        self.next_index += 1;
+        Ok(())
+    }
+
+    fn end(self) -> WriteResult<Encoded> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for StructSerializer {
+    type Ok = Encoded;
+    type Error = WriteError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> WriteResult<()> {
+        self.push(key, value)
+    }
+
+    fn skip_field(&mut self, _key: &'static str) -> WriteResult<()> {
+        self.next_index += 1;
+        Ok(())
+    }
+
+    fn end(self) -> WriteResult<Encoded> {
+        self.finish()
+    }
+}
+
+/// Deserializes a single value, given its type ID and content.
+struct ValueDeserializer<'de> {
+    type_id: TypeId,
+    /// Offset of the value's TLV, which errors are attributed to.
+    offset: usize,
+    data: BytesRef<'de>,
+}
+
+impl<'de> ValueDeserializer<'de> {
+    fn null(offset: usize) -> Self {
+        ValueDeserializer {
+            type_id: TypeId::Null,
+            offset,
+            data: BytesRef::from_slice(&[]),
+        }
+    }
+
+    /// Reads a value of type `type_id` from `data`.
+    fn read(data: &mut BytesRef<'de>, type_id: TypeId) -> ParseResult<Self> {
+        let offset = data.offset();
+        let data = read_value_for_typeid(data, type_id).map_err(|e| e.or_offset(offset))?;
+        Ok(ValueDeserializer {
+            type_id,
+            offset,
+            data,
+        })
+    }
+
+    /// Deserializes this value with `seed`, attributing errors that no
+    /// nested value has claimed to this value's offset.
+    fn deserialize_seed<S: DeserializeSeed<'de>>(self, seed: S) -> ParseResult<S::Value> {
+        let offset = self.offset;
+        seed.deserialize(self).map_err(|e| e.or_offset(offset))
+    }
+
+    fn expect(&self, expected: TypeId) -> ParseResult<()> {
+        if self.type_id != expected {
+            return Err(ParseError::new(ParseErrorKind::TypeMismatch {
+                expected: expected as u8,
+                actual: self.type_id as u8,
+            }));
+        }
+        Ok(())
+    }
+
+    fn parse<T: Relish>(mut self) -> ParseResult<T> {
+        self.expect(T::TYPE)?;
+        T::parse_value(&mut self.data)
+    }
+
+    fn read_type_id(data: &mut BytesRef<'de>) -> ParseResult<TypeId> {
+        let type_byte = read_byte(data)?;
+        TypeId::from_byte(type_byte)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidTypeId(type_byte)))
+    }
+
+    fn array(mut self) -> ParseResult<ArrayAccess<'de>> {
+        self.expect(TypeId::Array)?;
+        let elem_type = Self::read_type_id(&mut self.data)?;
+        Ok(ArrayAccess {
+            elem_type,
+            data: self.data,
+        })
+    }
+
+    fn fields(self) -> ParseResult<FieldAccess<'de>> {
+        self.expect(TypeId::Struct)?;
+        Ok(FieldAccess {
+            data: self.data,
+            last_id: None,
+        })
+    }
+
+    fn variant(mut self) -> ParseResult<(u8, ValueDeserializer<'de>)> {
+        self.expect(TypeId::Enum)?;
+        let variant_id = read_byte(&mut self.data)?;
+        if variant_id & 0x80 != 0 {
+            return Err(ParseError::new(ParseErrorKind::InvalidFieldId(variant_id)));
+        }
+        let offset = self.data.offset();
+        let type_id = Self::read_type_id(&mut self.data)?;
+        let mut value = Self::read(&mut self.data, type_id)?;
+        value.offset = offset;
+        if !self.data.is_empty() {
+            return Err(ParseError::new(ParseErrorKind::ExtraData {
+                bytes_remaining: self.data.len(),
+            })
+            .with_offset(self.data.offset()));
+        }
+        Ok((variant_id, value))
+    }
+}
+
+macro_rules! deserialize_primitive {
+    ($method:ident, $visit:ident, $ty:ty) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+            visitor.$visit(self.parse::<$ty>()?)
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = ParseError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        match self.type_id {
+            TypeId::Null => visitor.visit_unit(),
+            TypeId::Bool => visitor.visit_bool(self.parse()?),
+            TypeId::U8 => visitor.visit_u8(self.parse()?),
+            TypeId::U16 => visitor.visit_u16(self.parse()?),
+            TypeId::U32 => visitor.visit_u32(self.parse()?),
+            TypeId::U64 => visitor.visit_u64(self.parse()?),
+            TypeId::U128 => visitor.visit_u128(self.parse()?),
+            TypeId::I8 => visitor.visit_i8(self.parse()?),
+            TypeId::I16 => visitor.visit_i16(self.parse()?),
+            TypeId::I32 => visitor.visit_i32(self.parse()?),
+            TypeId::I64 => visitor.visit_i64(self.parse()?),
+            TypeId::I128 => visitor.visit_i128(self.parse()?),
+            TypeId::F32 => visitor.visit_f32(self.parse()?),
+            TypeId::F64 => visitor.visit_f64(self.parse()?),
+            TypeId::Timestamp => visitor.visit_u64(u64::parse_value(&mut { self.data })?),
+            TypeId::String => self.deserialize_str(visitor),
+            TypeId::Array => visitor.visit_seq(self.array()?),
+            TypeId::Map => {
+                let mut data = self.data;
+                let key_type = Self::read_type_id(&mut data)?;
+                let value_type = Self::read_type_id(&mut data)?;
+                visitor.visit_map(MapAccess {
+                    key_type,
+                    value_type,
+                    data,
+                })
+            }
+            TypeId::Struct => visitor.visit_map(StructAccess {
+                fields: self.fields()?,
+                names: None,
+                value: None,
+            }),
+            TypeId::Enum => {
+                let (variant_id, value) = self.variant()?;
+                visitor.visit_map(VariantMapAccess {
+                    variant_id,
+                    value: Some(value),
+                })
+            }
+        }
+    }
+
+    deserialize_primitive!(deserialize_bool, visit_bool, bool);
+    deserialize_primitive!(deserialize_u8, visit_u8, u8);
+    deserialize_primitive!(deserialize_u16, visit_u16, u16);
+    deserialize_primitive!(deserialize_u32, visit_u32, u32);
+    deserialize_primitive!(deserialize_u64, visit_u64, u64);
+    deserialize_primitive!(deserialize_u128, visit_u128, u128);
+    deserialize_primitive!(deserialize_i8, visit_i8, i8);
+    deserialize_primitive!(deserialize_i16, visit_i16, i16);
+    deserialize_primitive!(deserialize_i32, visit_i32, i32);
+    deserialize_primitive!(deserialize_i64, visit_i64, i64);
+    deserialize_primitive!(deserialize_i128, visit_i128, i128);
+    deserialize_primitive!(deserialize_f32, visit_f32, f32);
+    deserialize_primitive!(deserialize_f64, visit_f64, f64);
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        self.expect(TypeId::String)?;
+        let s = std::str::from_utf8(self.data.as_slice())
+            .map_err(|_| ParseError::new(ParseErrorKind::InvalidUtf8))?;
+        visitor.visit_borrowed_str(s)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        let array = self.array()?;
+        if array.elem_type != TypeId::U8 {
+            return Err(ParseError::new(ParseErrorKind::TypeMismatch {
+                expected: TypeId::U8 as u8,
+                actual: array.elem_type as u8,
+            }));
+        }
+        visitor.visit_borrowed_bytes(array.data.as_slice())
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        match self.type_id {
+            TypeId::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        self.expect(TypeId::Null)?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> ParseResult<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> ParseResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        visitor.visit_seq(self.array()?)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> ParseResult<V::Value> {
+        let mut access = TupleAccess {
+            fields: self.fields()?,
+            next_index: 0,
+            len,
+            peeked: None,
+            peeked_offset: 0,
+        };
+        let value = visitor.visit_seq(&mut access)?;
+        access.finish()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> ParseResult<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        self.expect(TypeId::Map)?;
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> ParseResult<V::Value> {
+        visitor.visit_map(StructAccess {
+            fields: self.fields()?,
+            names: Some(fields),
+            value: None,
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> ParseResult<V::Value> {
+        let (variant_id, value) = self.variant()?;
+        let variant = field_name(variants, variant_id)?
+            .ok_or_else(|| ParseError::new(ParseErrorKind::UnknownVariant(variant_id)))?;
+        visitor.visit_enum(EnumAccess { variant, value })
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+struct ArrayAccess<'de> {
+    elem_type: TypeId,
+    data: BytesRef<'de>,
+}
+
+impl<'de> de::SeqAccess<'de> for ArrayAccess<'de> {
+    type Error = ParseError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> ParseResult<Option<T::Value>> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+        check_not_zero_size(&self.data, &[self.elem_type])?;
+        ValueDeserializer::read(&mut self.data, self.elem_type)?
+            .deserialize_seed(seed)
+            .map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match self.elem_type.length() {
+            TypeLength::Fixed(0) | TypeLength::Varsize => None,
+            TypeLength::Fixed(size) => Some(self.data.len() / size),
+        }
+    }
+}
+
+struct MapAccess<'de> {
+    key_type: TypeId,
+    value_type: TypeId,
+    data: BytesRef<'de>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = ParseError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> ParseResult<Option<K::Value>> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+        check_not_zero_size(&self.data, &[self.key_type, self.value_type])?;
+        ValueDeserializer::read(&mut self.data, self.key_type)?
+            .deserialize_seed(seed)
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> ParseResult<V::Value> {
+        ValueDeserializer::read(&mut self.data, self.value_type)?.deserialize_seed(seed)
+    }
+}
+
+/// Iterates over the fields of a struct, enforcing field ID order.
+struct FieldAccess<'de> {
+    data: BytesRef<'de>,
+    last_id: Option<u8>,
+}
+
+impl<'de> FieldAccess<'de> {
+    fn next_field(&mut self) -> ParseResult<Option<(u8, ValueDeserializer<'de>)>> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+
+        let field_offset = self.data.offset();
+        let field_id = read_byte(&mut self.data)?;
+        if field_id & 0x80 != 0 {
+            return Err(
+                ParseError::new(ParseErrorKind::InvalidFieldId(field_id)).with_offset(field_offset)
+            );
+        }
+        if let Some(last_id) = self.last_id
+            && field_id <= last_id
+        {
+            return Err(ParseError::new(ParseErrorKind::FieldOrderViolation {
+                previous: last_id,
+                current: field_id,
+            })
+            .with_offset(field_offset));
+        }
+        self.last_id = Some(field_id);
+
+        let offset = self.data.offset();
+        let type_id = ValueDeserializer::read_type_id(&mut self.data)?;
+        let mut value = ValueDeserializer::read(&mut self.data, type_id)?;
+        value.offset = offset;
+        Ok(Some((field_id, value)))
+    }
+}
+
+/// Presents a struct as a map, keyed by field name when the target's field
+/// names are known and by field ID otherwise. Fields with unknown IDs are
+/// skipped.
+struct StructAccess<'de> {
+    fields: FieldAccess<'de>,
+    names: Option<&'static [&'static str]>,
+    value: Option<ValueDeserializer<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for StructAccess<'de> {
+    type Error = ParseError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> ParseResult<Option<K::Value>> {
+        while let Some((field_id, value)) = self.fields.next_field()? {
+            let key =
+                match self.names {
+                    None => seed
+                        .deserialize(IntoDeserializer::<ParseError>::into_deserializer(field_id))?,
+                    Some(names) => match field_name(names, field_id)? {
+                        Some(name) => seed
+                            .deserialize(IntoDeserializer::<ParseError>::into_deserializer(name))?,
+                        None => continue,
+                    },
+                };
+            self.value = Some(value);
+            return Ok(Some(key));
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> ParseResult<V::Value> {
+        let value = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        value.deserialize_seed(seed)
+    }
+}
+
+/// Presents a struct with field IDs `0..len` as a sequence. Omitted fields
+/// are presented as `Null`, so that `None` elements round trip.
+struct TupleAccess<'de> {
+    fields: FieldAccess<'de>,
+    next_index: usize,
+    len: usize,
+    peeked: Option<(u8, ValueDeserializer<'de>)>,
+    peeked_offset: usize,
+}
+
+impl TupleAccess<'_> {
+    /// Fails with [`ParseErrorKind::ExtraData`] if any fields were not read.
+    fn finish(self) -> ParseResult<()> {
+        if self.peeked.is_some() {
+            let end = self.fields.data.offset() + self.fields.data.len();
+            return Err(ParseError::new(ParseErrorKind::ExtraData {
+                bytes_remaining: end - self.peeked_offset,
+            })
+            .with_offset(self.peeked_offset));
+        }
+        check_empty(&self.fields.data)
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for TupleAccess<'de> {
+    type Error = ParseError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> ParseResult<Option<T::Value>> {
+        if self.next_index >= self.len {
+            return Ok(None);
+        }
+
+        if self.peeked.is_none() {
+            self.peeked_offset = self.fields.data.offset();
+            self.peeked = self.fields.next_field()?;
+        }
+        let value = match self.peeked.take() {
+            Some((field_id, value)) if field_id as usize == self.next_index => value,
+            peeked => {
+                self.peeked = peeked;
+                ValueDeserializer::null(self.fields.data.offset())
+            }
+        };
+        self.next_index += 1;
+        value.deserialize_seed(seed).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.next_index)
+    }
+}
+
+/// Presents an enum as a single-entry map from variant ID to value, for
+/// targets that don't know the enum's variants.
+struct VariantMapAccess<'de> {
+    variant_id: u8,
+    value: Option<ValueDeserializer<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for VariantMapAccess<'de> {
+    type Error = ParseError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> ParseResult<Option<K::Value>> {
+        if self.value.is_none() {
+            return Ok(None);
+        }
+        seed.deserialize(self.variant_id.into_deserializer())
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> ParseResult<V::Value> {
+        let value = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        value.deserialize_seed(seed)
+    }
+}
+
+struct EnumAccess<'de> {
+    variant: &'static str,
+    value: ValueDeserializer<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = ParseError;
+    type Variant = ValueDeserializer<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> ParseResult<(V::Value, ValueDeserializer<'de>)> {
+        let variant = seed.deserialize(IntoDeserializer::<ParseError>::into_deserializer(
+            self.variant,
+        ))?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for ValueDeserializer<'de> {
+    type Error = ParseError;
+
+    fn unit_variant(self) -> ParseResult<()> {
+        self.expect(TypeId::Null)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> ParseResult<T::Value> {
+        self.deserialize_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> ParseResult<V::Value> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> ParseResult<V::Value> {
+        de::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use ::serde::{Deserialize, Serialize};
+
+    use super::{from_slice, to_vec};
+    use crate::{ParseError, ParseErrorKind, WriteErrorKind};
+
+    fn assert_roundtrips<T>(value: T, bytes: &[u8])
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+    {
+        assert_eq!(to_vec(&value).as_deref(), Ok(bytes));
+        assert_eq!(from_slice::<T>(bytes), Ok(value));
+    }
+
+    #[test]
+    fn test_primitives() {
+        assert_roundtrips(42u32, &[0x04, 0x2A, 0x00, 0x00, 0x00]);
+        assert_roundtrips(-1i8, &[0x07, 0xFF]);
+        assert_roundtrips(true, &[0x01, 0xFF]);
+        assert_roundtrips("hi".to_string(), &[0x0E, 0x04, b'h', b'i']);
+        assert_roundtrips('é', &[0x0E, 0x04, 0xC3, 0xA9]);
+        assert_roundtrips((), &[0x00]);
+        assert_roundtrips(None::<u32>, &[0x00]);
+        assert_roundtrips(Some(7u8), &[0x02, 0x07]);
+
+        assert_eq!(
+            from_slice::<u64>(&[0x04, 0x2A, 0x00, 0x00, 0x00]),
+            Err(ParseError::new(ParseErrorKind::TypeMismatch {
+                expected: 0x05,
+                actual: 0x04
+            })
+            .with_offset(0))
+        );
+    }
+
+    #[test]
+    fn test_struct() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Person {
+            name: String,
+            age: u32,
+            email: Option<String>,
+        }
+
+        // Identical to #[derive(Relish)] with field IDs 0, 1 and 2.
+        assert_roundtrips(
+            Person {
+                name: "Alice".to_string(),
+                age: 30,
+                email: None,
+            },
+            &[
+                0x11, 0x1C, 0x00, 0x0E, 0x0A, b'A', b'l', b'i', b'c', b'e', 0x01, 0x04, 0x1E, 0x00,
+                0x00, 0x00,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_explicit_field_ids() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Ids {
+            #[serde(rename = "5")]
+            b: u8,
+            #[serde(rename = "2")]
+            a: u8,
+        }
+
+        assert_roundtrips(
+            Ids { b: 1, a: 2 },
+            &[0x11, 0x0C, 0x02, 0x02, 0x02, 0x05, 0x02, 0x01],
+        );
+
+        // Unknown fields are skipped.
+        assert_eq!(
+            from_slice::<Ids>(&[
+                0x11, 0x12, 0x02, 0x02, 0x02, 0x03, 0x02, 0x09, 0x05, 0x02, 0x01
+            ]),
+            Ok(Ids { b: 1, a: 2 })
+        );
+    }
+
+    #[test]
+    fn test_skip_serializing_if() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Skipping {
+            #[serde(skip_serializing_if = "Vec::is_empty", default)]
+            a: Vec<u8>,
+            b: u8,
+        }
+
+        // `b` keeps field ID 1 even when `a` is skipped.
+        assert_roundtrips(
+            Skipping { a: vec![], b: 3 },
+            &[0x11, 0x06, 0x01, 0x02, 0x03],
+        );
+    }
+
+    #[test]
+    fn test_duplicate_field_id() {
+        #[derive(Serialize)]
+        struct Duplicate {
+            #[serde(rename = "1")]
+            a: u8,
+            b: u8,
+        }
+
+        assert_eq!(
+            to_vec(&Duplicate { a: 1, b: 2 }).unwrap_err().kind(),
+            &WriteErrorKind::DuplicateFieldId(1)
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_field_id() {
+        #[derive(Debug, Serialize, Deserialize)]
+        struct OutOfRange {
+            a: u8,
+            #[serde(rename = "300")]
+            b: u8,
+        }
+
+        // Not treated as a name, which would give it its declaration index
+        assert_eq!(
+            to_vec(&OutOfRange { a: 1, b: 2 }).unwrap_err().kind(),
+            &WriteErrorKind::FieldIdTooLarge(u8::MAX)
+        );
+        assert_eq!(
+            from_slice::<OutOfRange>(&[0x11, 0x06, 0x00, 0x02, 0x01])
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::InvalidFieldId(u8::MAX)
+        );
+    }
+
+    #[test]
+    fn test_sequences_and_maps() {
+        assert_roundtrips(vec![1u16, 2], &[0x0F, 0x0A, 0x03, 0x01, 0x00, 0x02, 0x00]);
+        assert_roundtrips(Vec::<u16>::new(), &[0x0F, 0x02, 0x00]);
+
+        let mut map = BTreeMap::new();
+        map.insert("a".to_string(), 1u8);
+        assert_roundtrips(map, &[0x10, 0x0A, 0x0E, 0x02, 0x02, b'a', 0x01]);
+
+        assert_eq!(
+            to_vec(&vec![Some(1u8), None]).unwrap_err().kind(),
+            &WriteErrorKind::MixedElementTypes {
+                expected: 0x02,
+                actual: 0x00
+            }
+        );
+    }
+
+    #[test]
+    fn test_tuples() {
+        assert_roundtrips(
+            (1u8, "x".to_string()),
+            &[0x11, 0x0E, 0x00, 0x02, 0x01, 0x01, 0x0E, 0x02, b'x'],
+        );
+        assert_roundtrips((None::<u8>, 2u8), &[0x11, 0x06, 0x01, 0x02, 0x02]);
+
+        // Fields beyond the tuple's length are not skipped
+        let data = [0x11, 0x0C, 0x00, 0x02, 0x01, 0x01, 0x02, 0x02];
+        assert_eq!(
+            from_slice::<(u8,)>(&data),
+            Err(ParseError::new(ParseErrorKind::ExtraData { bytes_remaining: 3 }).with_offset(5))
+        );
+        let data = [0x11, 0x0C, 0x00, 0x02, 0x01, 0x02, 0x02, 0x02];
+        assert_eq!(
+            from_slice::<(u8, Option<u8>)>(&data),
+            Err(ParseError::new(ParseErrorKind::ExtraData { bytes_remaining: 3 }).with_offset(5))
+        );
+    }
+
+    #[test]
+    fn test_enums() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Shape {
+            Empty,
+            Circle(u8),
+            #[serde(rename = "9")]
+            Rect {
+                w: u8,
+                h: u8,
+            },
+            Pair(u8, u8),
+        }
+
+        assert_roundtrips(Shape::Empty, &[0x12, 0x04, 0x00, 0x00]);
+        assert_roundtrips(Shape::Circle(3), &[0x12, 0x06, 0x01, 0x02, 0x03]);
+        assert_roundtrips(
+            Shape::Rect { w: 1, h: 2 },
+            &[
+                0x12, 0x12, 0x09, 0x11, 0x0C, 0x00, 0x02, 0x01, 0x01, 0x02, 0x02,
+            ],
+        );
+        assert_roundtrips(
+            Shape::Pair(1, 2),
+            &[
+                0x12, 0x12, 0x03, 0x11, 0x0C, 0x00, 0x02, 0x01, 0x01, 0x02, 0x02,
+            ],
+        );
+
+        assert_eq!(
+            from_slice::<Shape>(&[0x12, 0x04, 0x05, 0x00]),
+            Err(ParseError::new(ParseErrorKind::UnknownVariant(5)).with_offset(0))
+        );
+    }
+
+    #[test]
+    fn test_borrowed() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Borrowed<'a> {
+            name: &'a str,
+            data: &'a [u8],
+        }
+
+        let data = [
+            0x11, 0x16, 0x00, 0x0E, 0x04, b'h', b'i', 0x01, 0x0F, 0x06, 0x02, 0x01, 0x02,
+        ];
+        let parsed: Borrowed<'_> = from_slice(&data).unwrap();
+        assert_eq!(
+            parsed,
+            Borrowed {
+                name: "hi",
+                data: &[1, 2]
+            }
+        );
+        assert_eq!(parsed.name.as_ptr(), data[5..].as_ptr());
+    }
+}
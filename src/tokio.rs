@@ -0,0 +1,281 @@
+//! Framed Relish messages over [tokio](https://tokio.rs) I/O.
+//!
+//! A top-level Relish value carries its own framing: its type ID, and for
+//! varsize types a length prefix, determine exactly how many bytes it spans.
+//! [`RelishCodec`] uses this to split a byte stream into messages with
+//! `tokio_util::codec::Framed`, and [`from_async_reader`] and
+//! [`to_async_writer`] read and write a single message.
+
+use std::marker::PhantomData;
+
+use ::tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use ::tokio_util::codec::{Decoder, Encoder};
+use bytes::{BufMut, BytesMut};
+
+use crate::record::frame_length;
+use crate::{
+    DEFAULT_MAX_MESSAGE_SIZE, ParseError, ParseErrorKind, ParseResult, Relish, RelishBorrow,
+    WriteError, WriteErrorKind, WriteResult, parse,
+};
+
+/// A [`Decoder`] and [`Encoder`] for back-to-back top-level Relish values of
+/// type `T`.
+///
+/// Decoding waits until a complete frame has arrived before parsing it.
+/// Frames larger than the maximum frame size, [`DEFAULT_MAX_MESSAGE_SIZE`]
+/// unless configured, are rejected with [`ParseErrorKind::MessageTooLarge`]
+/// as soon as their length prefix is read, and are refused by the encoder
+/// with [`WriteErrorKind::MessageTooLarge`].
+#[derive(Debug)]
+pub struct RelishCodec<T> {
+    max_frame_size: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> RelishCodec<T> {
+    /// Creates a codec with the default maximum frame size.
+    pub fn new() -> Self {
+        RelishCodec {
+            max_frame_size: DEFAULT_MAX_MESSAGE_SIZE,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the maximum size in bytes of a single frame, including its type
+    /// ID and length prefix.
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+}
+
+impl<T> Default for RelishCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for RelishCodec<T> {
+    fn clone(&self) -> Self {
+        RelishCodec {
+            max_frame_size: self.max_frame_size,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Relish> RelishCodec<T> {
+    /// Returns the length of the frame at the start of `src`, or, if its
+    /// header has not fully arrived, the length of the header.
+    fn frame_length(&self, src: &[u8]) -> ParseResult<usize> {
+        let frame_len = frame_length::<T>(src)?;
+        if frame_len > self.max_frame_size {
+            return Err(ParseError::new(ParseErrorKind::MessageTooLarge {
+                size: frame_len,
+                max: self.max_frame_size,
+            }));
+        }
+        Ok(frame_len)
+    }
+}
+
+impl<T: Relish> Decoder for RelishCodec<T> {
+    type Item = T;
+    type Error = ParseError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> ParseResult<Option<T>> {
+        if src.is_empty() {
+            return Ok(None);
+        }
+
+        let needed = self.frame_length(src)?;
+        if src.len() < needed {
+            src.reserve(needed - src.len());
+            return Ok(None);
+        }
+
+        parse(src.split_to(needed).freeze()).map(Some)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> ParseResult<Option<T>> {
+        match self.decode(src)? {
+            Some(value) => Ok(Some(value)),
+            None if src.is_empty() => Ok(None),
+            None => Err(ParseError::new(ParseErrorKind::InsufficientData {
+                needed: self.frame_length(src)?,
+                available: src.len(),
+            })),
+        }
+    }
+}
+
+impl<T: Relish> Encoder<&T> for RelishCodec<T> {
+    type Error = WriteError;
+
+    fn encode(&mut self, item: &T, dst: &mut BytesMut) -> WriteResult<()> {
+        let start = dst.len();
+        dst.put_u8(item.value_type() as u8);
+        if let Err(e) = item.write_value(dst) {
+            dst.truncate(start);
+            return Err(e);
+        }
+
+        let size = dst.len() - start;
+        if size > self.max_frame_size {
+            dst.truncate(start);
+            return Err(WriteError::new(WriteErrorKind::MessageTooLarge {
+                size,
+                max: self.max_frame_size,
+            }));
+        }
+        Ok(())
+    }
+}
+
+impl<T: Relish> Encoder<T> for RelishCodec<T> {
+    type Error = WriteError;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> WriteResult<()> {
+        <Self as Encoder<&T>>::encode(self, &item, dst)
+    }
+}
+
+/// Reads a single value in Relish binary format from `reader`.
+///
+/// Like [`from_reader`](crate::from_reader), exactly the bytes of one value
+/// are consumed, and messages larger than [`DEFAULT_MAX_MESSAGE_SIZE`] are
+/// rejected before their content is read. Use `RelishCodec` to read a
+/// sequence of values.
+pub async fn from_async_reader<T: Relish, R: AsyncRead + Unpin>(mut reader: R) -> ParseResult<T> {
+    let mut codec = RelishCodec::<T>::new();
+    let mut buf = BytesMut::new();
+    loop {
+        if let Some(value) = codec.decode(&mut buf)? {
+            return Ok(value);
+        }
+
+        let needed = codec.frame_length(&buf)?;
+        let read = (&mut reader)
+            .take((needed - buf.len()) as u64)
+            .read_buf(&mut buf)
+            .await?;
+        if read == 0 {
+            return Err(ParseError::new(ParseErrorKind::InsufficientData {
+                needed,
+                available: buf.len(),
+            }));
+        }
+    }
+}
+
+/// Serialize a value to Relish binary format and write it to `writer`.
+///
+/// The message is encoded in full before it is written, and `writer` is not
+/// flushed.
+pub async fn to_async_writer<'a, T: RelishBorrow<'a>, W: AsyncWrite + Unpin>(
+    mut writer: W,
+    value: &T,
+) -> WriteResult<()> {
+    let data = crate::to_vec(value)?;
+    writer.write_all(&data).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use super::{RelishCodec, from_async_reader, to_async_writer};
+    use crate::{ParseError, ParseErrorKind, WriteErrorKind, to_vec};
+
+    #[test]
+    fn test_codec_decode() {
+        let mut data = to_vec(&"x".repeat(200)).unwrap();
+        data.extend(to_vec(&"y".to_string()).unwrap());
+
+        // Frames are only decoded once they have fully arrived
+        let mut codec = RelishCodec::<String>::new();
+        let mut buf = BytesMut::new();
+        let mut decoded = Vec::new();
+        for &byte in &data {
+            buf.extend_from_slice(&[byte]);
+            if let Some(value) = codec.decode(&mut buf).unwrap() {
+                decoded.push(value);
+            }
+        }
+        assert_eq!(decoded, vec!["x".repeat(200), "y".to_string()]);
+        assert!(buf.is_empty());
+        assert_eq!(codec.decode_eof(&mut buf), Ok(None));
+
+        let mut buf = BytesMut::from(&[0x0E, 0x06, b'a'][..]);
+        assert_eq!(
+            codec.decode_eof(&mut buf),
+            Err(ParseError::new(ParseErrorKind::InsufficientData {
+                needed: 5,
+                available: 3
+            }))
+        );
+
+        let mut buf = BytesMut::from(&[0x04u8][..]);
+        assert_eq!(
+            codec.decode(&mut buf),
+            Err(ParseError::new(ParseErrorKind::TypeMismatch {
+                expected: 0x0E,
+                actual: 0x04
+            }))
+        );
+    }
+
+    #[test]
+    fn test_codec_max_frame_size() {
+        let mut codec = RelishCodec::<Vec<u8>>::new().max_frame_size(16);
+
+        // Rejected from the length prefix alone
+        let mut buf = BytesMut::from(&[0x0Fu8, 0xFF, 0xFF, 0xFF, 0x7F][..]);
+        assert_eq!(
+            codec.decode(&mut buf),
+            Err(ParseError::new(ParseErrorKind::MessageTooLarge {
+                size: 5 + (u32::MAX >> 2) as usize,
+                max: 16
+            }))
+        );
+
+        let mut buf = BytesMut::new();
+        codec.encode(&vec![1u8; 12], &mut buf).unwrap();
+        assert_eq!(codec.decode(&mut buf), Ok(Some(vec![1u8; 12])));
+        assert_eq!(
+            codec.encode(vec![1u8; 14], &mut buf).unwrap_err().kind(),
+            &WriteErrorKind::MessageTooLarge { size: 17, max: 16 }
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_async_reader_writer() {
+        let mut data = Vec::new();
+        to_async_writer(&mut data, &vec![1u32, 2]).await.unwrap();
+        to_async_writer(&mut data, &"rest".to_string())
+            .await
+            .unwrap();
+        assert_eq!(data[..11], to_vec(&vec![1u32, 2]).unwrap());
+
+        // Only the first message is consumed
+        let mut reader = &data[..];
+        assert_eq!(
+            from_async_reader::<Vec<u32>, _>(&mut reader).await,
+            Ok(vec![1, 2])
+        );
+        assert_eq!(
+            from_async_reader::<String, _>(&mut reader).await,
+            Ok("rest".to_string())
+        );
+        assert_eq!(
+            from_async_reader::<String, _>(&mut reader).await,
+            Err(ParseError::new(ParseErrorKind::InsufficientData {
+                needed: 1,
+                available: 0
+            }))
+        );
+    }
+}
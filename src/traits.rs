@@ -1,4 +1,5 @@
 use crate::buf::BytesRef;
+use crate::write::Sink;
 
 /// Core trait for types that can be serialized and deserialized in the Relish format.
 pub trait Relish: Sized {
@@ -14,11 +15,112 @@ pub trait Relish: Sized {
     /// data is malformed.
     fn parse_value(data: &mut BytesRef) -> crate::ParseResult<Self>;
 
-    /// Write the value to the buffer including length prefix for varsize types.
-    fn write_value(&self, buffer: &mut Vec<u8>) -> crate::WriteResult<()>;
+    /// Write the value to the sink including length prefix for varsize types.
+    ///
+    /// Types that contain other values should write their content through
+    /// [`Sink::write_varsize`] instead of computing its length first.
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()>;
 
     /// Returns the total byte length of the serialized value including length prefix for varsize types.
     fn value_length(&self) -> usize;
+
+    /// Returns the TypeId this particular value is written with.
+    ///
+    /// This is [`Self::TYPE`] for every statically typed value. Dynamically
+    /// typed values, such as [`Value`](crate::Value), override it along with
+    /// [`accepts_type`](Self::accepts_type) and
+    /// [`parse_typed_value`](Self::parse_typed_value).
+    fn value_type(&self) -> crate::TypeId {
+        Self::TYPE
+    }
+
+    /// Returns whether a value encoded with `type_id` can be parsed as this type.
+    fn accepts_type(type_id: crate::TypeId) -> bool {
+        type_id == Self::TYPE
+    }
+
+    /// Parse a value that was encoded with `type_id`, which has already been
+    /// checked with [`accepts_type`](Self::accepts_type).
+    fn parse_typed_value(type_id: crate::TypeId, data: &mut BytesRef) -> crate::ParseResult<Self> {
+        let _ = type_id;
+        Self::parse_value(data)
+    }
+}
+
+/// Trait for types that can be parsed by borrowing from the input buffer.
+///
+/// Every [`Relish`] type implements this trait for all lifetimes. Types that
+/// point into the input, such as `&'de str` and `&'de [u8]`, implement only
+/// this trait and are parsed with [`parse_borrowed`](crate::parse_borrowed).
+pub trait RelishBorrow<'de>: Sized {
+    /// The TypeId that identifies this type in the Relish binary format.
+    const TYPE: crate::TypeId;
+
+    /// Parse a value from the given data buffer, borrowing from it where
+    /// possible.
+    ///
+    /// As with [`Relish::parse_value`], the data buffer contains only the
+    /// value portion of the TLV encoding.
+    fn parse_borrowed_value(data: &mut BytesRef<'de>) -> crate::ParseResult<Self>;
+
+    /// Write the value to the sink including length prefix for varsize types.
+    ///
+    /// Types that contain other values should write their content through
+    /// [`Sink::write_varsize`] instead of computing its length first.
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()>;
+
+    /// Returns the total byte length of the serialized value including length prefix for varsize types.
+    fn value_length(&self) -> usize;
+
+    /// See [`Relish::value_type`].
+    fn value_type(&self) -> crate::TypeId {
+        Self::TYPE
+    }
+
+    /// See [`Relish::accepts_type`].
+    fn accepts_type(type_id: crate::TypeId) -> bool {
+        type_id == Self::TYPE
+    }
+
+    /// See [`Relish::parse_typed_value`].
+    fn parse_borrowed_typed_value(
+        type_id: crate::TypeId,
+        data: &mut BytesRef<'de>,
+    ) -> crate::ParseResult<Self> {
+        let _ = type_id;
+        Self::parse_borrowed_value(data)
+    }
+}
+
+impl<'de, T: Relish> RelishBorrow<'de> for T {
+    const TYPE: crate::TypeId = T::TYPE;
+
+    fn parse_borrowed_value(data: &mut BytesRef<'de>) -> crate::ParseResult<Self> {
+        T::parse_value(data)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        Relish::write_value(self, buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        Relish::value_length(self)
+    }
+
+    fn value_type(&self) -> crate::TypeId {
+        Relish::value_type(self)
+    }
+
+    fn accepts_type(type_id: crate::TypeId) -> bool {
+        T::accepts_type(type_id)
+    }
+
+    fn parse_borrowed_typed_value(
+        type_id: crate::TypeId,
+        data: &mut BytesRef<'de>,
+    ) -> crate::ParseResult<Self> {
+        T::parse_typed_value(type_id, data)
+    }
 }
 
 pub trait FieldValue: Sized {
@@ -52,6 +154,39 @@ impl<T: Relish> FieldValue for Option<T> {
     }
 }
 
+/// The [`FieldValue`] counterpart for fields of structs that borrow from the
+/// input buffer.
+pub trait BorrowedFieldValue<'de>: Sized {
+    type T: RelishBorrow<'de>;
+
+    fn as_relish(&self) -> Option<&Self::T>;
+    fn from_option(v: Option<Self::T>) -> crate::ParseResult<Self>;
+}
+
+impl<'de, T: RelishBorrow<'de>> BorrowedFieldValue<'de> for T {
+    type T = T;
+
+    fn as_relish(&self) -> Option<&Self::T> {
+        Some(self)
+    }
+
+    fn from_option(v: Option<Self::T>) -> crate::ParseResult<Self> {
+        v.ok_or_else(|| crate::ParseError::new(crate::ParseErrorKind::MissingRequiredField))
+    }
+}
+
+impl<'de, T: RelishBorrow<'de>> BorrowedFieldValue<'de> for Option<T> {
+    type T = T;
+
+    fn as_relish(&self) -> Option<&Self::T> {
+        self.as_ref()
+    }
+
+    fn from_option(v: Option<Self::T>) -> crate::ParseResult<Self> {
+        Ok(v)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::FieldValue;
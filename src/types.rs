@@ -1,15 +1,37 @@
-use std::collections::HashMap;
-use std::hash::{BuildHasher, Hash};
-use std::mem;
-use std::sync::Arc;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque, btree_map};
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(any(feature = "std", feature = "hashbrown", feature = "indexmap"))]
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+use core::mem;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use core::num::{
+    NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128, NonZeroU8, NonZeroU16, NonZeroU32,
+    NonZeroU64, NonZeroU128, Wrapping,
+};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::Relish;
 use crate::buf::BytesRef;
-use crate::parse::{parse_value_for_typeid, read_byte};
-use crate::{ParseError, ParseErrorKind, ParseResult};
+use crate::parse::{StructReader, check_not_zero_size, parse_value_for_typeid, read_byte};
+use crate::write::Sink;
+use crate::{FieldValue, Relish};
+use crate::{ParseError, ParseErrorKind, ParseResult, PathFrame};
 
 #[doc(hidden)]
-pub fn write_tagged_varint_length(buffer: &mut Vec<u8>, length: usize) -> crate::WriteResult<()> {
+pub fn write_tagged_varint_length<W: Sink + ?Sized>(
+    buffer: &mut W,
+    length: usize,
+) -> crate::WriteResult<()> {
     if length > (1 << 31) - 1 {
         return Err(crate::WriteError::new(
             crate::WriteErrorKind::ContentTooLarge(length),
@@ -17,10 +39,10 @@ pub fn write_tagged_varint_length(buffer: &mut Vec<u8>, length: usize) -> crate:
     }
 
     if length < 128 {
-        buffer.push((length << 1) as u8);
+        buffer.write_byte((length << 1) as u8)?;
     } else {
         let tagged_value = ((length as u32) << 1) | 1;
-        buffer.extend_from_slice(&tagged_value.to_le_bytes());
+        buffer.write_bytes(&tagged_value.to_le_bytes())?;
     }
     Ok(())
 }
@@ -30,6 +52,31 @@ pub fn tagged_varint_length_size(length: usize) -> usize {
     if length < 128 { 1 } else { 4 }
 }
 
+/// Returns the type ID shared by every element, or `T::TYPE` if there are
+/// none, since arrays and maps carry a single type for all their elements.
+fn element_type<'a, T: Relish + 'a>(
+    mut elements: impl Iterator<Item = &'a T>,
+) -> crate::WriteResult<TypeId> {
+    let Some(first) = elements.next() else {
+        return Ok(T::TYPE);
+    };
+
+    let expected = first.value_type();
+    for elem in elements {
+        let actual = elem.value_type();
+        if actual != expected {
+            return Err(crate::WriteError::new(
+                crate::WriteErrorKind::MixedElementTypes {
+                    expected: expected as u8,
+                    actual: actual as u8,
+                },
+            ));
+        }
+    }
+
+    Ok(expected)
+}
+
 /// Type identifiers used in the Relish binary format.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
@@ -96,13 +143,23 @@ impl TypeId {
 
     #[doc(hidden)]
     pub fn read_for_type<T: Relish>(data: &mut BytesRef) -> ParseResult<TypeId> {
+        TypeId::read_matching(data, T::TYPE, T::accepts_type)
+    }
+
+    /// Reads a type ID, failing with a mismatch against `expected` unless
+    /// `accepts` allows it.
+    pub(crate) fn read_matching(
+        data: &mut BytesRef,
+        expected: TypeId,
+        accepts: fn(TypeId) -> bool,
+    ) -> ParseResult<TypeId> {
         let type_byte = read_byte(data)?;
         let type_id = TypeId::from_byte(type_byte)
             .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidTypeId(type_byte)))?;
 
-        if type_id != T::TYPE {
+        if !accepts(type_id) {
             return Err(ParseError::new(ParseErrorKind::TypeMismatch {
-                expected: T::TYPE as u8,
+                expected: expected as u8,
                 actual: type_byte,
             }));
         }
@@ -136,6 +193,33 @@ impl TypeId {
     }
 }
 
+impl fmt::Display for TypeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TypeId::Null => "null",
+            TypeId::Bool => "bool",
+            TypeId::U8 => "u8",
+            TypeId::U16 => "u16",
+            TypeId::U32 => "u32",
+            TypeId::U64 => "u64",
+            TypeId::U128 => "u128",
+            TypeId::I8 => "i8",
+            TypeId::I16 => "i16",
+            TypeId::I32 => "i32",
+            TypeId::I64 => "i64",
+            TypeId::I128 => "i128",
+            TypeId::F32 => "f32",
+            TypeId::F64 => "f64",
+            TypeId::String => "string",
+            TypeId::Array => "array",
+            TypeId::Map => "map",
+            TypeId::Struct => "struct",
+            TypeId::Enum => "enum",
+            TypeId::Timestamp => "timestamp",
+        })
+    }
+}
+
 impl From<TypeId> for u8 {
     fn from(type_id: TypeId) -> u8 {
         type_id as u8
@@ -153,8 +237,8 @@ macro_rules! impl_relish_integer {
                 Ok(<$ty>::from_le_bytes(bytes))
             }
 
-            fn write_value(&self, buffer: &mut Vec<u8>) -> crate::WriteResult<()> {
-                buffer.extend_from_slice(&self.to_le_bytes());
+            fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+                buffer.write_bytes(&self.to_le_bytes())?;
                 Ok(())
             }
 
@@ -189,8 +273,8 @@ impl Relish for bool {
         }
     }
 
-    fn write_value(&self, buffer: &mut Vec<u8>) -> crate::WriteResult<()> {
-        buffer.push(if *self { 0xFF } else { 0x00 });
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        buffer.write_byte(if *self { 0xFF } else { 0x00 })?;
         Ok(())
     }
 
@@ -210,7 +294,23 @@ impl Relish for Null {
         Ok(Null)
     }
 
-    fn write_value(&self, _buffer: &mut Vec<u8>) -> crate::WriteResult<()> {
+    fn write_value<W: Sink + ?Sized>(&self, _buffer: &mut W) -> crate::WriteResult<()> {
+        Ok(())
+    }
+
+    fn value_length(&self) -> usize {
+        0
+    }
+}
+
+impl Relish for () {
+    const TYPE: TypeId = TypeId::Null;
+
+    fn parse_value(_data: &mut BytesRef) -> ParseResult<Self> {
+        Ok(())
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, _buffer: &mut W) -> crate::WriteResult<()> {
         Ok(())
     }
 
@@ -219,6 +319,60 @@ impl Relish for Null {
     }
 }
 
+/// Tuples are encoded as structs, with each element's position as its field
+/// ID.
+macro_rules! impl_relish_tuple {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name: Relish),+> Relish for ($($name,)+) {
+            const TYPE: TypeId = TypeId::Struct;
+
+            fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+                let mut parser = StructReader::new(data);
+                let value = ($(
+                    parser
+                        .read_value_for_field_id::<$name>($index)
+                        .and_then(<$name as FieldValue>::from_option)
+                        .map_err(|e| e.with_frame(PathFrame::Index($index)))?,
+                )+);
+                parser.finish()?;
+                Ok(value)
+            }
+
+            fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+                buffer.write_varsize(
+                    || 0 $(+ 2 + self.$index.value_length())+,
+                    |buffer| {
+                        $(
+                            buffer.write_byte($index)?;
+                            buffer.write_byte(self.$index.value_type() as u8)?;
+                            self.$index.write_value(buffer)?;
+                        )+
+                        Ok(())
+                    },
+                )
+            }
+
+            fn value_length(&self) -> usize {
+                let content_size = 0 $(+ 2 + self.$index.value_length())+;
+                tagged_varint_length_size(content_size) + content_size
+            }
+        }
+    };
+}
+
+impl_relish_tuple!(T0 0);
+impl_relish_tuple!(T0 0, T1 1);
+impl_relish_tuple!(T0 0, T1 1, T2 2);
+impl_relish_tuple!(T0 0, T1 1, T2 2, T3 3);
+impl_relish_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4);
+impl_relish_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5);
+impl_relish_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6);
+impl_relish_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7);
+impl_relish_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8);
+impl_relish_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9);
+impl_relish_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10);
+impl_relish_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10, T11 11);
+
 #[cfg(feature = "chrono")]
 impl Relish for chrono::DateTime<chrono::Utc> {
     const TYPE: TypeId = TypeId::Timestamp;
@@ -233,11 +387,11 @@ impl Relish for chrono::DateTime<chrono::Utc> {
             .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidTimestamp(timestamp)))
     }
 
-    fn write_value(&self, buffer: &mut Vec<u8>) -> crate::WriteResult<()> {
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
         let timestamp = self.timestamp();
         let timestamp_u64 = u64::try_from(timestamp)
             .map_err(|_| crate::WriteError::new(crate::WriteErrorKind::InvalidTimestamp))?;
-        buffer.extend_from_slice(&timestamp_u64.to_le_bytes());
+        buffer.write_bytes(&timestamp_u64.to_le_bytes())?;
         Ok(())
     }
 
@@ -246,22 +400,174 @@ impl Relish for chrono::DateTime<chrono::Utc> {
     }
 }
 
+#[cfg(feature = "time")]
+impl Relish for time::OffsetDateTime {
+    const TYPE: TypeId = TypeId::Timestamp;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        let timestamp = u64::parse_value(data)?;
+        i64::try_from(timestamp)
+            .ok()
+            .and_then(|timestamp| time::OffsetDateTime::from_unix_timestamp(timestamp).ok())
+            .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidTimestamp(timestamp)))
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        let timestamp = u64::try_from(self.unix_timestamp())
+            .map_err(|_| crate::WriteError::new(crate::WriteErrorKind::InvalidTimestamp))?;
+        timestamp.write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        mem::size_of::<u64>()
+    }
+}
+
+#[cfg(feature = "jiff")]
+impl Relish for jiff::Timestamp {
+    const TYPE: TypeId = TypeId::Timestamp;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        let timestamp = u64::parse_value(data)?;
+        i64::try_from(timestamp)
+            .ok()
+            .and_then(|timestamp| jiff::Timestamp::from_second(timestamp).ok())
+            .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidTimestamp(timestamp)))
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        let timestamp = u64::try_from(self.as_second())
+            .map_err(|_| crate::WriteError::new(crate::WriteErrorKind::InvalidTimestamp))?;
+        timestamp.write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        mem::size_of::<u64>()
+    }
+}
+
+/// `Uuid` is encoded as a `u128` holding its big-endian numeric value.
+#[cfg(feature = "uuid")]
+impl Relish for uuid::Uuid {
+    const TYPE: TypeId = TypeId::U128;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        u128::parse_value(data).map(uuid::Uuid::from_u128)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        self.as_u128().write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        mem::size_of::<u128>()
+    }
+}
+
+/// `OrderedFloat` is transparent on the wire.
+#[cfg(feature = "ordered-float")]
+impl<T: Relish> Relish for ordered_float::OrderedFloat<T> {
+    const TYPE: TypeId = T::TYPE;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        T::parse_value(data).map(ordered_float::OrderedFloat)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        self.0.write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        self.0.value_length()
+    }
+}
+
+/// Parses a string value with `T`'s `FromStr` impl, failing with
+/// [`ParseErrorKind::InvalidFormat`] if it is rejected.
+#[cfg(any(feature = "rust_decimal", feature = "url", feature = "semver"))]
+fn parse_from_str<T: core::str::FromStr>(
+    data: &mut BytesRef,
+    format: &'static str,
+) -> ParseResult<T> {
+    data.allocate(data.len())?;
+    core::str::from_utf8(data.as_ref())
+        .map_err(|_| ParseError::new(ParseErrorKind::InvalidUtf8))?
+        .parse()
+        .map_err(|_| ParseError::new(ParseErrorKind::InvalidFormat(format)))
+}
+
+/// `Decimal` is encoded as a string in its `Display` form, such as `"1.50"`,
+/// which preserves its scale.
+#[cfg(feature = "rust_decimal")]
+impl Relish for rust_decimal::Decimal {
+    const TYPE: TypeId = TypeId::String;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        parse_from_str(data, "decimal")
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        self.to_string().write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        self.to_string().value_length()
+    }
+}
+
+/// `Url` is encoded as a string.
+#[cfg(feature = "url")]
+impl Relish for url::Url {
+    const TYPE: TypeId = TypeId::String;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        parse_from_str(data, "URL")
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        crate::RelishBorrow::write_value(&self.as_str(), buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        crate::RelishBorrow::value_length(&self.as_str())
+    }
+}
+
+/// `Version` is encoded as a string, such as `"1.2.3-beta.1"`.
+#[cfg(feature = "semver")]
+impl Relish for semver::Version {
+    const TYPE: TypeId = TypeId::String;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        parse_from_str(data, "semver version")
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        self.to_string().write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        self.to_string().value_length()
+    }
+}
+
 impl Relish for String {
     const TYPE: TypeId = TypeId::String;
 
     fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        data.allocate(data.len())?;
         let result = String::from_utf8(data.as_ref().to_vec())
             .map_err(|_| ParseError::new(ParseErrorKind::InvalidUtf8))?;
         Ok(result)
     }
 
-    fn write_value(&self, buffer: &mut Vec<u8>) -> crate::WriteResult<()> {
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
         let bytes = self.as_bytes();
         let len = bytes.len();
         let prefix_len = tagged_varint_length_size(len);
         buffer.reserve(prefix_len + len);
         write_tagged_varint_length(buffer, len)?;
-        buffer.extend_from_slice(bytes);
+        buffer.write_bytes(bytes)?;
         Ok(())
     }
 
@@ -275,18 +581,19 @@ impl Relish for Arc<str> {
     const TYPE: TypeId = TypeId::String;
 
     fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
-        let s = std::str::from_utf8(data.as_ref())
+        data.allocate(data.len())?;
+        let s = core::str::from_utf8(data.as_ref())
             .map_err(|_| ParseError::new(ParseErrorKind::InvalidUtf8))?;
         Ok(Arc::from(s))
     }
 
-    fn write_value(&self, buffer: &mut Vec<u8>) -> crate::WriteResult<()> {
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
         let bytes = self.as_bytes();
         let len = bytes.len();
         let prefix_len = tagged_varint_length_size(len);
         buffer.reserve(prefix_len + len);
         write_tagged_varint_length(buffer, len)?;
-        buffer.extend_from_slice(bytes);
+        buffer.write_bytes(bytes)?;
         Ok(())
     }
 
@@ -300,18 +607,19 @@ impl Relish for Box<str> {
     const TYPE: TypeId = TypeId::String;
 
     fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
-        let s = std::str::from_utf8(data.as_ref())
+        data.allocate(data.len())?;
+        let s = core::str::from_utf8(data.as_ref())
             .map_err(|_| ParseError::new(ParseErrorKind::InvalidUtf8))?;
         Ok(Box::from(s))
     }
 
-    fn write_value(&self, buffer: &mut Vec<u8>) -> crate::WriteResult<()> {
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
         let bytes = self.as_bytes();
         let len = bytes.len();
         let prefix_len = tagged_varint_length_size(len);
         buffer.reserve(prefix_len + len);
         write_tagged_varint_length(buffer, len)?;
-        buffer.extend_from_slice(bytes);
+        buffer.write_bytes(bytes)?;
         Ok(())
     }
 
@@ -321,217 +629,1006 @@ impl Relish for Box<str> {
     }
 }
 
-impl<T: Relish> Relish for Vec<T> {
-    const TYPE: TypeId = TypeId::Array;
-
-    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
-        let elem_type = TypeId::read_for_type::<T>(data)?;
-
-        let mut elements = Vec::new();
-        if let TypeLength::Fixed(size) = elem_type.length() {
-            elements.reserve(data.len() / size);
-        }
+impl<'de> crate::RelishBorrow<'de> for &'de str {
+    const TYPE: TypeId = TypeId::String;
 
-        while !data.is_empty() {
-            elements.push(parse_value_for_typeid::<T>(data, elem_type)?);
-        }
+    fn parse_borrowed_value(data: &mut BytesRef<'de>) -> ParseResult<Self> {
+        core::str::from_utf8(data.as_slice())
+            .map_err(|_| ParseError::new(ParseErrorKind::InvalidUtf8))
+    }
 
-        Ok(elements)
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        let bytes = self.as_bytes();
+        let len = bytes.len();
+        let prefix_len = tagged_varint_length_size(len);
+        buffer.reserve(prefix_len + len);
+        write_tagged_varint_length(buffer, len)?;
+        buffer.write_bytes(bytes)?;
+        Ok(())
     }
 
-    fn write_value(&self, buffer: &mut Vec<u8>) -> crate::WriteResult<()> {
-        let mut content_len = 1;
-        for elem in self {
-            content_len += elem.value_length();
-        }
+    fn value_length(&self) -> usize {
+        let len = self.len();
+        tagged_varint_length_size(len) + len
+    }
+}
 
-        let prefix_len = tagged_varint_length_size(content_len);
-        buffer.reserve(prefix_len + content_len);
-        write_tagged_varint_length(buffer, content_len)?;
-        buffer.push(T::TYPE as u8);
+impl<'de> crate::RelishBorrow<'de> for Cow<'de, str> {
+    const TYPE: TypeId = TypeId::String;
 
-        for elem in self {
-            elem.write_value(buffer)?;
-        }
+    fn parse_borrowed_value(data: &mut BytesRef<'de>) -> ParseResult<Self> {
+        <&'de str as crate::RelishBorrow<'de>>::parse_borrowed_value(data).map(Cow::Borrowed)
+    }
 
-        Ok(())
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        crate::RelishBorrow::write_value(&self.as_ref(), buffer)
     }
 
     fn value_length(&self) -> usize {
-        let mut content_size = 1;
-        for elem in self {
-            content_size += elem.value_length();
-        }
-        tagged_varint_length_size(content_size) + content_size
+        crate::RelishBorrow::value_length(&self.as_ref())
     }
 }
 
-impl<T: Relish> Relish for Box<[T]> {
-    const TYPE: TypeId = TypeId::Array;
+/// Smart pointers are transparent on the wire: they are encoded exactly as
+/// the value they point to.
+macro_rules! impl_relish_pointer {
+    ($($pointer:ident),+) => {$(
+        impl<T: Relish> Relish for $pointer<T> {
+            const TYPE: TypeId = T::TYPE;
 
-    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
-        let elem_type = TypeId::read_for_type::<T>(data)?;
+            fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+                T::parse_value(data).map($pointer::new)
+            }
 
-        let mut elements = Vec::new();
-        if let TypeLength::Fixed(size) = elem_type.length() {
-            elements.reserve(data.len() / size);
-        }
+            fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+                (**self).write_value(buffer)
+            }
 
-        while !data.is_empty() {
-            elements.push(parse_value_for_typeid::<T>(data, elem_type)?);
-        }
+            fn value_length(&self) -> usize {
+                (**self).value_length()
+            }
 
-        Ok(elements.into_boxed_slice())
-    }
+            fn value_type(&self) -> TypeId {
+                (**self).value_type()
+            }
 
-    fn write_value(&self, buffer: &mut Vec<u8>) -> crate::WriteResult<()> {
-        let mut content_len = 1;
-        for elem in self {
-            content_len += elem.value_length();
+            fn accepts_type(type_id: TypeId) -> bool {
+                T::accepts_type(type_id)
+            }
+
+            fn parse_typed_value(type_id: TypeId, data: &mut BytesRef) -> ParseResult<Self> {
+                T::parse_typed_value(type_id, data).map($pointer::new)
+            }
         }
+    )+};
+}
 
-        let prefix_len = tagged_varint_length_size(content_len);
-        buffer.reserve(prefix_len + content_len);
-        write_tagged_varint_length(buffer, content_len)?;
-        buffer.push(T::TYPE as u8);
+impl_relish_pointer!(Box, Rc, Arc);
 
-        for elem in self {
-            elem.write_value(buffer)?;
-        }
+/// Like the smart pointers, `Cow` is transparent on the wire. Parsed values
+/// are always owned.
+impl<T: Relish + Clone> Relish for Cow<'_, T> {
+    const TYPE: TypeId = T::TYPE;
 
-        Ok(())
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        T::parse_value(data).map(Cow::Owned)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        self.as_ref().write_value(buffer)
     }
 
     fn value_length(&self) -> usize {
-        let mut content_size = 1;
-        for elem in self {
-            content_size += elem.value_length();
+        self.as_ref().value_length()
+    }
+
+    fn value_type(&self) -> TypeId {
+        self.as_ref().value_type()
+    }
+
+    fn accepts_type(type_id: TypeId) -> bool {
+        T::accepts_type(type_id)
+    }
+
+    fn parse_typed_value(type_id: TypeId, data: &mut BytesRef) -> ParseResult<Self> {
+        T::parse_typed_value(type_id, data).map(Cow::Owned)
+    }
+}
+
+/// A collection that is encoded as a Relish array.
+trait ArrayCollection<T> {
+    fn with_capacity(capacity: usize) -> Self;
+
+    /// Adds `elem`, returning false if the collection already contained it.
+    fn push_element(&mut self, elem: T) -> bool;
+}
+
+/// A collection that is encoded as a Relish map.
+trait MapCollection<K, V> {
+    fn with_capacity(capacity: usize) -> Self;
+
+    /// Adds an entry, returning false if the collection already contained
+    /// `key`.
+    fn push_entry(&mut self, key: K, value: V) -> bool;
+}
+
+fn parse_array<T: Relish, C: ArrayCollection<T>>(data: &mut BytesRef) -> ParseResult<C> {
+    let elem_type = TypeId::read_for_type::<T>(data)?;
+
+    let capacity = match elem_type.length() {
+        TypeLength::Fixed(size @ 1..) => data.len() / size,
+        _ => 0,
+    };
+    let mut elements = C::with_capacity(capacity);
+
+    let mut count = 0;
+    while !data.is_empty() {
+        check_not_zero_size(data, &[elem_type])?;
+        data.add_element::<T>(count)?;
+        let offset = data.offset();
+        let elem = parse_value_for_typeid::<T>(data, elem_type)
+            .map_err(|e| e.with_frame(PathFrame::Index(count)))?;
+        if !elements.push_element(elem) {
+            return Err(ParseError::new(ParseErrorKind::DuplicateSetElement)
+                .with_offset(offset)
+                .with_frame(PathFrame::Index(count)));
         }
-        tagged_varint_length_size(content_size) + content_size
+        count += 1;
     }
+
+    Ok(elements)
 }
 
-#[cfg(feature = "smallvec")]
-impl<A: smallvec::Array> Relish for smallvec::SmallVec<A>
-where
-    A::Item: Relish,
-{
-    const TYPE: TypeId = TypeId::Array;
+fn write_array<'a, T: Relish + 'a, W: Sink + ?Sized>(
+    elements: impl Iterator<Item = &'a T> + Clone,
+    buffer: &mut W,
+) -> crate::WriteResult<()> {
+    let elem_type = element_type(elements.clone())?;
+
+    buffer.write_varsize(
+        || 1 + elements.clone().map(Relish::value_length).sum::<usize>(),
+        |buffer| {
+            buffer.write_byte(elem_type as u8)?;
+            for elem in elements.clone() {
+                elem.write_value(buffer)?;
+            }
+            Ok(())
+        },
+    )
+}
 
-    fn parse_value(data: &mut BytesRef) -> crate::ParseResult<Self> {
-        let elem_type = TypeId::read_for_type::<A::Item>(data)?;
+/// Like [`write_array`], for collections with no meaningful order, whose
+/// elements are sorted by their encoding if `buffer` is canonical.
+fn write_set<'a, T: Relish + 'a, W: Sink + ?Sized>(
+    elements: impl Iterator<Item = &'a T> + Clone,
+    buffer: &mut W,
+) -> crate::WriteResult<()> {
+    let elem_type = element_type(elements.clone())?;
+
+    buffer.write_varsize(
+        || 1 + elements.clone().map(Relish::value_length).sum::<usize>(),
+        |buffer| {
+            buffer.write_byte(elem_type as u8)?;
+            write_entries(elements.clone().map(|elem| (elem, &())), buffer)
+        },
+    )
+}
+
+fn array_value_length<'a, T: Relish + 'a>(elements: impl Iterator<Item = &'a T>) -> usize {
+    let content_size = 1 + elements.map(Relish::value_length).sum::<usize>();
+    tagged_varint_length_size(content_size) + content_size
+}
 
-        let mut elements = smallvec::SmallVec::<A>::new();
-        if let TypeLength::Fixed(size) = elem_type.length() {
-            elements.reserve(data.len() / size);
+fn parse_map<K: Relish, V: Relish, C: MapCollection<K, V>>(data: &mut BytesRef) -> ParseResult<C> {
+    let key_type = TypeId::read_for_type::<K>(data)?;
+    let value_type = TypeId::read_for_type::<V>(data)?;
+
+    let mut map = C::with_capacity(0);
+    let mut count = 0;
+    while !data.is_empty() {
+        check_not_zero_size(data, &[key_type, value_type])?;
+        data.add_element::<(K, V)>(count)?;
+        let offset = data.offset();
+        let key = parse_value_for_typeid::<K>(data, key_type)
+            .map_err(|e| e.with_frame(PathFrame::MapKey(count)))?;
+        let value = parse_value_for_typeid::<V>(data, value_type)
+            .map_err(|e| e.with_frame(PathFrame::MapValue(count)))?;
+        if !map.push_entry(key, value) {
+            return Err(ParseError::new(ParseErrorKind::DuplicateMapKey)
+                .with_offset(offset)
+                .with_frame(PathFrame::MapKey(count)));
         }
+        count += 1;
+    }
 
-        while !data.is_empty() {
-            elements.push(parse_value_for_typeid::<A::Item>(data, elem_type)?);
+    Ok(map)
+}
+
+fn write_map<'a, K: Relish + 'a, V: Relish + 'a, W: Sink + ?Sized>(
+    entries: impl Iterator<Item = (&'a K, &'a V)> + Clone,
+    buffer: &mut W,
+) -> crate::WriteResult<()> {
+    let key_type = element_type(entries.clone().map(|(k, _)| k))?;
+    let value_type = element_type(entries.clone().map(|(_, v)| v))?;
+
+    buffer.write_varsize(
+        || {
+            2 + entries
+                .clone()
+                .map(|(k, v)| k.value_length() + v.value_length())
+                .sum::<usize>()
+        },
+        |buffer| {
+            buffer.write_byte(key_type as u8)?;
+            buffer.write_byte(value_type as u8)?;
+            write_entries(entries.clone(), buffer)
+        },
+    )
+}
+
+/// Writes the entries of a map, sorted by their encoded keys if `buffer` is
+/// canonical. Keys that only differ before being made canonical are rejected
+/// as duplicates.
+pub(crate) fn write_entries<'a, K: Relish + 'a, V: Relish + 'a, W: Sink + ?Sized>(
+    entries: impl Iterator<Item = (&'a K, &'a V)>,
+    buffer: &mut W,
+) -> crate::WriteResult<()> {
+    if !buffer.is_canonical() {
+        for (k, v) in entries {
+            k.write_value(buffer)?;
+            v.write_value(buffer)?;
         }
+        return Ok(());
+    }
 
-        Ok(elements)
+    let mut encoded = entries
+        .map(|(k, v)| Ok((crate::canonical::encode_value(k)?, v)))
+        .collect::<crate::WriteResult<Vec<_>>>()?;
+    encoded.sort_by(|(a, _), (b, _)| a.cmp(b));
+    if encoded.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+        return Err(crate::WriteError::new(
+            crate::WriteErrorKind::DuplicateMapKey,
+        ));
+    }
+    for (k, v) in encoded {
+        buffer.write_bytes(&k)?;
+        v.write_value(buffer)?;
     }
+    Ok(())
+}
 
-    fn write_value(&self, buffer: &mut Vec<u8>) -> crate::WriteResult<()> {
-        let mut content_len = 1;
-        for elem in self {
-            content_len += elem.value_length();
-        }
+fn map_value_length<'a, K: Relish + 'a, V: Relish + 'a>(
+    entries: impl Iterator<Item = (&'a K, &'a V)>,
+) -> usize {
+    let content_size = 2 + entries
+        .map(|(k, v)| k.value_length() + v.value_length())
+        .sum::<usize>();
+    tagged_varint_length_size(content_size) + content_size
+}
 
-        let prefix_len = tagged_varint_length_size(content_len);
-        buffer.reserve(prefix_len + content_len);
-        write_tagged_varint_length(buffer, content_len)?;
-        buffer.push(A::Item::TYPE as u8);
+impl<T> ArrayCollection<T> for Vec<T> {
+    fn with_capacity(capacity: usize) -> Self {
+        Vec::with_capacity(capacity)
+    }
 
-        for elem in self {
-            elem.write_value(buffer)?;
-        }
+    fn push_element(&mut self, elem: T) -> bool {
+        self.push(elem);
+        true
+    }
+}
 
-        Ok(())
+impl<T: Relish> Relish for Vec<T> {
+    const TYPE: TypeId = TypeId::Array;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        parse_array(data)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        write_array(self.iter(), buffer)
     }
 
     fn value_length(&self) -> usize {
-        let mut content_size = 1;
-        for elem in self {
-            content_size += elem.value_length();
-        }
-        tagged_varint_length_size(content_size) + content_size
+        array_value_length(self.iter())
     }
 }
 
-impl Relish for bytes::Bytes {
+impl<T: Relish> Relish for Box<[T]> {
     const TYPE: TypeId = TypeId::Array;
 
     fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
-        TypeId::read_for_type::<u8>(data)?;
+        parse_array::<T, Vec<T>>(data).map(Vec::into_boxed_slice)
+    }
 
-        Ok(data.to_bytes())
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        write_array(self.iter(), buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        array_value_length(self.iter())
+    }
+}
+
+/// Fixed-size arrays are encoded as arrays, and fail to parse unless the
+/// encoded array has exactly `N` elements.
+impl<T: Relish, const N: usize> Relish for [T; N] {
+    const TYPE: TypeId = TypeId::Array;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        let elements: Vec<T> = parse_array(data)?;
+        elements.try_into().map_err(|elements: Vec<T>| {
+            ParseError::new(ParseErrorKind::ArrayLengthMismatch {
+                expected: N,
+                actual: elements.len(),
+            })
+        })
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        write_array(self.iter(), buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        array_value_length(self.iter())
+    }
+}
+
+impl<T> ArrayCollection<T> for VecDeque<T> {
+    fn with_capacity(capacity: usize) -> Self {
+        VecDeque::with_capacity(capacity)
+    }
+
+    fn push_element(&mut self, elem: T) -> bool {
+        self.push_back(elem);
+        true
+    }
+}
+
+impl<T: Relish> Relish for VecDeque<T> {
+    const TYPE: TypeId = TypeId::Array;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        parse_array(data)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        write_array(self.iter(), buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        array_value_length(self.iter())
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array> ArrayCollection<A::Item> for smallvec::SmallVec<A> {
+    fn with_capacity(capacity: usize) -> Self {
+        smallvec::SmallVec::with_capacity(capacity)
+    }
+
+    fn push_element(&mut self, elem: A::Item) -> bool {
+        self.push(elem);
+        true
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array> Relish for smallvec::SmallVec<A>
+where
+    A::Item: Relish,
+{
+    const TYPE: TypeId = TypeId::Array;
+
+    fn parse_value(data: &mut BytesRef) -> crate::ParseResult<Self> {
+        parse_array(data)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        write_array(self.iter(), buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        array_value_length(self.iter())
+    }
+}
+
+impl<T: Ord> ArrayCollection<T> for BTreeSet<T> {
+    fn with_capacity(_capacity: usize) -> Self {
+        BTreeSet::new()
+    }
+
+    fn push_element(&mut self, elem: T) -> bool {
+        self.insert(elem)
+    }
+}
+
+impl<T: Relish + Ord> Relish for BTreeSet<T> {
+    const TYPE: TypeId = TypeId::Array;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        parse_array(data)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        write_set(self.iter(), buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        array_value_length(self.iter())
+    }
+}
+
+impl Relish for bytes::Bytes {
+    const TYPE: TypeId = TypeId::Array;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        TypeId::read_for_type::<u8>(data)?;
+
+        Ok(data.to_bytes())
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        let content_len = 1 + self.len();
+
+        let prefix_len = tagged_varint_length_size(content_len);
+        buffer.reserve(prefix_len + content_len);
+        write_tagged_varint_length(buffer, content_len)?;
+        buffer.write_byte(TypeId::U8 as u8)?;
+        buffer.write_bytes(self)?;
+
+        Ok(())
+    }
+
+    fn value_length(&self) -> usize {
+        let content_size = 1 + self.len();
+        tagged_varint_length_size(content_size) + content_size
+    }
+}
+
+impl<'de> crate::RelishBorrow<'de> for &'de [u8] {
+    const TYPE: TypeId = TypeId::Array;
+
+    fn parse_borrowed_value(data: &mut BytesRef<'de>) -> ParseResult<Self> {
+        TypeId::read_for_type::<u8>(data)?;
+
+        Ok(data.as_slice())
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        let content_len = 1 + self.len();
+
+        let prefix_len = tagged_varint_length_size(content_len);
+        buffer.reserve(prefix_len + content_len);
+        write_tagged_varint_length(buffer, content_len)?;
+        buffer.write_byte(TypeId::U8 as u8)?;
+        buffer.write_bytes(self)?;
+
+        Ok(())
+    }
+
+    fn value_length(&self) -> usize {
+        let content_size = 1 + self.len();
+        tagged_varint_length_size(content_size) + content_size
+    }
+}
+
+/// Implements `HashSet` and `HashMap` support for the module at `$module`,
+/// which is either `std::collections` or `hashbrown`.
+#[cfg(any(feature = "std", feature = "hashbrown"))]
+macro_rules! impl_relish_hash_collections {
+    ($($module:ident)::+) => {
+        impl<T: Eq + Hash, S: BuildHasher + Default> ArrayCollection<T>
+            for $($module)::+::HashSet<T, S>
+        {
+            fn with_capacity(capacity: usize) -> Self {
+                Self::with_capacity_and_hasher(capacity, S::default())
+            }
+
+            fn push_element(&mut self, elem: T) -> bool {
+                self.insert(elem)
+            }
+        }
+
+        impl<T: Relish + Eq + Hash, S: BuildHasher + Default> Relish
+            for $($module)::+::HashSet<T, S>
+        {
+            const TYPE: TypeId = TypeId::Array;
+
+            fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+                parse_array(data)
+            }
+
+            fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+                write_set(self.iter(), buffer)
+            }
+
+            fn value_length(&self) -> usize {
+                array_value_length(self.iter())
+            }
+        }
+
+        impl<K: Eq + Hash, V, S: BuildHasher + Default> MapCollection<K, V>
+            for $($module)::+::HashMap<K, V, S>
+        {
+            fn with_capacity(capacity: usize) -> Self {
+                Self::with_capacity_and_hasher(capacity, S::default())
+            }
+
+            fn push_entry(&mut self, key: K, value: V) -> bool {
+                self.insert(key, value).is_none()
+            }
+        }
+
+        impl<K: Relish + Eq + Hash, V: Relish, S: BuildHasher + Default> Relish
+            for $($module)::+::HashMap<K, V, S>
+        {
+            const TYPE: TypeId = TypeId::Map;
+
+            fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+                parse_map(data)
+            }
+
+            fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+                write_map(self.iter(), buffer)
+            }
+
+            fn value_length(&self) -> usize {
+                map_value_length(self.iter())
+            }
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+impl_relish_hash_collections!(std::collections);
+
+#[cfg(feature = "hashbrown")]
+impl_relish_hash_collections!(hashbrown);
+
+impl<K: Ord, V> MapCollection<K, V> for BTreeMap<K, V> {
+    fn with_capacity(_capacity: usize) -> Self {
+        BTreeMap::new()
+    }
+
+    fn push_entry(&mut self, key: K, value: V) -> bool {
+        match self.entry(key) {
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(value);
+                true
+            }
+            btree_map::Entry::Occupied(_) => false,
+        }
+    }
+}
+
+impl<K: Relish + Ord, V: Relish> Relish for BTreeMap<K, V> {
+    const TYPE: TypeId = TypeId::Map;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        parse_map(data)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        write_map(self.iter(), buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        map_value_length(self.iter())
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K: Eq + Hash, V, S: BuildHasher + Default> MapCollection<K, V>
+    for indexmap::IndexMap<K, V, S>
+{
+    fn with_capacity(capacity: usize) -> Self {
+        indexmap::IndexMap::with_capacity_and_hasher(capacity, S::default())
+    }
+
+    fn push_entry(&mut self, key: K, value: V) -> bool {
+        match self.entry(key) {
+            indexmap::map::Entry::Vacant(entry) => {
+                entry.insert(value);
+                true
+            }
+            indexmap::map::Entry::Occupied(_) => false,
+        }
+    }
+}
+
+/// Entries are written, and parsed, in insertion order.
+#[cfg(feature = "indexmap")]
+impl<K: Relish + Eq + Hash, V: Relish, S: BuildHasher + Default> Relish
+    for indexmap::IndexMap<K, V, S>
+{
+    const TYPE: TypeId = TypeId::Map;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        parse_map(data)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        write_map(self.iter(), buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        map_value_length(self.iter())
+    }
+}
+
+fn read_variant_id(data: &mut BytesRef) -> ParseResult<u8> {
+    let variant_id = read_byte(data)?;
+    if variant_id & 0x80 != 0 {
+        return Err(ParseError::new(ParseErrorKind::InvalidFieldId(variant_id)));
+    }
+    Ok(variant_id)
+}
+
+fn parse_variant<T: Relish>(
+    data: &mut BytesRef,
+    type_name: &'static str,
+    variant_name: &'static str,
+    variant_id: u8,
+) -> ParseResult<T> {
+    crate::parse_tlv(data).map_err(|e| {
+        e.with_frame(PathFrame::Variant {
+            type_name,
+            variant_name,
+            variant_id,
+        })
+    })
+}
+
+fn write_variant<T: Relish, W: Sink + ?Sized>(
+    buffer: &mut W,
+    variant_id: u8,
+    value: &T,
+) -> crate::WriteResult<()> {
+    buffer.write_varsize(
+        || 2 + value.value_length(),
+        |buffer| {
+            buffer.write_byte(variant_id)?;
+            buffer.write_byte(value.value_type() as u8)?;
+            value.write_value(buffer)
+        },
+    )
+}
+
+fn variant_length<T: Relish>(value: &T) -> usize {
+    let content_size = 2 + value.value_length();
+    tagged_varint_length_size(content_size) + content_size
+}
+
+/// `Result` is encoded as an enum, with `Ok` as variant 0 and `Err` as
+/// variant 1.
+impl<T: Relish, E: Relish> Relish for Result<T, E> {
+    const TYPE: TypeId = TypeId::Enum;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        match read_variant_id(data)? {
+            0 => parse_variant(data, "Result", "Ok", 0).map(Ok),
+            1 => parse_variant(data, "Result", "Err", 1).map(Err),
+            variant_id => Err(ParseError::new(ParseErrorKind::UnknownVariant(variant_id))),
+        }
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        match self {
+            Ok(value) => write_variant(buffer, 0, value),
+            Err(value) => write_variant(buffer, 1, value),
+        }
+    }
+
+    fn value_length(&self) -> usize {
+        match self {
+            Ok(value) => variant_length(value),
+            Err(value) => variant_length(value),
+        }
+    }
+}
+
+/// `PhantomData` is encoded as a null.
+impl<T: ?Sized> Relish for PhantomData<T> {
+    const TYPE: TypeId = TypeId::Null;
+
+    fn parse_value(_data: &mut BytesRef) -> ParseResult<Self> {
+        Ok(PhantomData)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, _buffer: &mut W) -> crate::WriteResult<()> {
+        Ok(())
+    }
+
+    fn value_length(&self) -> usize {
+        0
+    }
+}
+
+/// `Wrapping` is transparent on the wire.
+impl<T: Relish> Relish for Wrapping<T> {
+    const TYPE: TypeId = T::TYPE;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        T::parse_value(data).map(Wrapping)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        self.0.write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        self.0.value_length()
+    }
+
+    fn value_type(&self) -> TypeId {
+        self.0.value_type()
+    }
+
+    fn accepts_type(type_id: TypeId) -> bool {
+        T::accepts_type(type_id)
+    }
+
+    fn parse_typed_value(type_id: TypeId, data: &mut BytesRef) -> ParseResult<Self> {
+        T::parse_typed_value(type_id, data).map(Wrapping)
+    }
+}
+
+/// Non-zero integers are encoded as the underlying integer, and fail to parse
+/// if it is zero.
+macro_rules! impl_relish_nonzero {
+    ($($nonzero:ty => $int:ty),+) => {$(
+        impl Relish for $nonzero {
+            const TYPE: TypeId = <$int>::TYPE;
+
+            fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+                <$nonzero>::new(<$int>::parse_value(data)?)
+                    .ok_or_else(|| ParseError::new(ParseErrorKind::ZeroValue))
+            }
+
+            fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+                self.get().write_value(buffer)
+            }
+
+            fn value_length(&self) -> usize {
+                mem::size_of::<$int>()
+            }
+        }
+    )+};
+}
+
+impl_relish_nonzero!(
+    NonZeroU8 => u8,
+    NonZeroU16 => u16,
+    NonZeroU32 => u32,
+    NonZeroU64 => u64,
+    NonZeroU128 => u128,
+    NonZeroI8 => i8,
+    NonZeroI16 => i16,
+    NonZeroI32 => i32,
+    NonZeroI64 => i64,
+    NonZeroI128 => i128
+);
+
+/// `char` is encoded as a `u32` holding its Unicode scalar value.
+impl Relish for char {
+    const TYPE: TypeId = TypeId::U32;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        let value = u32::parse_value(data)?;
+        char::from_u32(value).ok_or_else(|| ParseError::new(ParseErrorKind::InvalidChar(value)))
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        u32::from(*self).write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        mem::size_of::<u32>()
+    }
+}
+
+/// `Duration` is encoded as a struct of its whole seconds (field 0, `u64`)
+/// and its nanoseconds (field 1, `u32`), which must be less than one second.
+impl Relish for Duration {
+    const TYPE: TypeId = TypeId::Struct;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        let (secs, nanos) = <(u64, u32)>::parse_value(data)?;
+        if nanos >= 1_000_000_000 {
+            return Err(ParseError::new(ParseErrorKind::InvalidDurationNanos(nanos)));
+        }
+        Ok(Duration::new(secs, nanos))
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        (self.as_secs(), self.subsec_nanos()).write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        (self.as_secs(), self.subsec_nanos()).value_length()
+    }
+}
+
+/// `SystemTime` is encoded as a timestamp of whole seconds since the Unix
+/// epoch. Sub-second precision is discarded, and times before the epoch
+/// cannot be written.
+#[cfg(feature = "std")]
+impl Relish for SystemTime {
+    const TYPE: TypeId = TypeId::Timestamp;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        let timestamp = u64::parse_value(data)?;
+        UNIX_EPOCH
+            .checked_add(Duration::from_secs(timestamp))
+            .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidTimestamp(timestamp)))
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        let since_epoch = self
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| crate::WriteError::new(crate::WriteErrorKind::InvalidTimestamp))?;
+        since_epoch.as_secs().write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        mem::size_of::<u64>()
+    }
+}
+
+/// `Ipv4Addr` is encoded as a `u32` holding its big-endian numeric value, so
+/// `1.2.3.4` is `0x01020304`.
+impl Relish for Ipv4Addr {
+    const TYPE: TypeId = TypeId::U32;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        u32::parse_value(data).map(Ipv4Addr::from_bits)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        self.to_bits().write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        mem::size_of::<u32>()
+    }
+}
+
+/// `Ipv6Addr` is encoded as a `u128` holding its big-endian numeric value.
+impl Relish for Ipv6Addr {
+    const TYPE: TypeId = TypeId::U128;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        u128::parse_value(data).map(Ipv6Addr::from_bits)
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        self.to_bits().write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        mem::size_of::<u128>()
+    }
+}
+
+/// `IpAddr` is encoded as an enum, with `V4` as variant 0 and `V6` as
+/// variant 1.
+impl Relish for IpAddr {
+    const TYPE: TypeId = TypeId::Enum;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        match read_variant_id(data)? {
+            0 => parse_variant(data, "IpAddr", "V4", 0).map(IpAddr::V4),
+            1 => parse_variant(data, "IpAddr", "V6", 1).map(IpAddr::V6),
+            variant_id => Err(ParseError::new(ParseErrorKind::UnknownVariant(variant_id))),
+        }
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        match self {
+            IpAddr::V4(addr) => write_variant(buffer, 0, addr),
+            IpAddr::V6(addr) => write_variant(buffer, 1, addr),
+        }
+    }
+
+    fn value_length(&self) -> usize {
+        match self {
+            IpAddr::V4(addr) => variant_length(addr),
+            IpAddr::V6(addr) => variant_length(addr),
+        }
+    }
+}
+
+/// `SocketAddrV4` is encoded as a struct of its IP address (field 0) and port
+/// (field 1, `u16`).
+impl Relish for SocketAddrV4 {
+    const TYPE: TypeId = TypeId::Struct;
+
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        let (ip, port) = Relish::parse_value(data)?;
+        Ok(SocketAddrV4::new(ip, port))
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        (*self.ip(), self.port()).write_value(buffer)
+    }
+
+    fn value_length(&self) -> usize {
+        (*self.ip(), self.port()).value_length()
     }
+}
 
-    fn write_value(&self, buffer: &mut Vec<u8>) -> crate::WriteResult<()> {
-        let content_len = 1 + self.len();
+/// `SocketAddrV6` is encoded as a struct of its IP address (field 0), port
+/// (field 1, `u16`), flow info (field 2, `u32`) and scope ID (field 3,
+/// `u32`).
+impl Relish for SocketAddrV6 {
+    const TYPE: TypeId = TypeId::Struct;
 
-        let prefix_len = tagged_varint_length_size(content_len);
-        buffer.reserve(prefix_len + content_len);
-        write_tagged_varint_length(buffer, content_len)?;
-        buffer.push(TypeId::U8 as u8);
-        buffer.extend_from_slice(self);
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        let (ip, port, flowinfo, scope_id) = Relish::parse_value(data)?;
+        Ok(SocketAddrV6::new(ip, port, flowinfo, scope_id))
+    }
 
-        Ok(())
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        socket_addr_v6_fields(self).write_value(buffer)
     }
 
     fn value_length(&self) -> usize {
-        let content_size = 1 + self.len();
-        tagged_varint_length_size(content_size) + content_size
+        socket_addr_v6_fields(self).value_length()
     }
 }
 
-impl<K: Relish + Eq + Hash, V: Relish, S: BuildHasher + Default> Relish for HashMap<K, V, S> {
-    const TYPE: TypeId = TypeId::Map;
+fn socket_addr_v6_fields(addr: &SocketAddrV6) -> (Ipv6Addr, u16, u32, u32) {
+    (*addr.ip(), addr.port(), addr.flowinfo(), addr.scope_id())
+}
 
-    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
-        let key_type = TypeId::read_for_type::<K>(data)?;
-        let value_type = TypeId::read_for_type::<V>(data)?;
+/// `SocketAddr` is encoded as an enum, with `V4` as variant 0 and `V6` as
+/// variant 1.
+impl Relish for SocketAddr {
+    const TYPE: TypeId = TypeId::Enum;
 
-        let mut map = HashMap::default();
-        while !data.is_empty() {
-            let key = parse_value_for_typeid::<K>(data, key_type)?;
-            let value = parse_value_for_typeid::<V>(data, value_type)?;
-            if map.insert(key, value).is_some() {
-                return Err(ParseError::new(ParseErrorKind::DuplicateMapKey));
-            }
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        match read_variant_id(data)? {
+            0 => parse_variant(data, "SocketAddr", "V4", 0).map(SocketAddr::V4),
+            1 => parse_variant(data, "SocketAddr", "V6", 1).map(SocketAddr::V6),
+            variant_id => Err(ParseError::new(ParseErrorKind::UnknownVariant(variant_id))),
         }
+    }
 
-        Ok(map)
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        match self {
+            SocketAddr::V4(addr) => write_variant(buffer, 0, addr),
+            SocketAddr::V6(addr) => write_variant(buffer, 1, addr),
+        }
     }
 
-    fn write_value(&self, buffer: &mut Vec<u8>) -> crate::WriteResult<()> {
-        let mut content_len = 2;
-        for (k, v) in self {
-            content_len += k.value_length() + v.value_length();
+    fn value_length(&self) -> usize {
+        match self {
+            SocketAddr::V4(addr) => variant_length(addr),
+            SocketAddr::V6(addr) => variant_length(addr),
         }
+    }
+}
 
-        let prefix_len = tagged_varint_length_size(content_len);
-        buffer.reserve(prefix_len + content_len);
-        write_tagged_varint_length(buffer, content_len)?;
-        buffer.push(K::TYPE as u8);
-        buffer.push(V::TYPE as u8);
+/// `PathBuf` is encoded as a string. Paths that are not valid UTF-8 cannot be
+/// written.
+#[cfg(feature = "std")]
+impl Relish for PathBuf {
+    const TYPE: TypeId = TypeId::String;
 
-        for (k, v) in self {
-            k.write_value(buffer)?;
-            v.write_value(buffer)?;
-        }
+    fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+        String::parse_value(data).map(PathBuf::from)
+    }
 
-        Ok(())
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> crate::WriteResult<()> {
+        let path = self
+            .to_str()
+            .ok_or_else(|| crate::WriteError::new(crate::WriteErrorKind::NonUtf8Path))?;
+        crate::RelishBorrow::write_value(&path, buffer)
     }
 
     fn value_length(&self) -> usize {
-        let mut content_size = 2;
-        for (k, v) in self {
-            content_size += k.value_length() + v.value_length();
-        }
-        tagged_varint_length_size(content_size) + content_size
+        let len = self.as_os_str().len();
+        tagged_varint_length_size(len) + len
     }
 }
 
@@ -540,7 +1637,10 @@ mod tests {
     use super::{tagged_varint_length_size, write_tagged_varint_length};
     use crate::buf::BytesRef;
     use crate::parse::read_tagged_varint_length;
-    use crate::{Null, ParseError, ParseErrorKind, ParseResult, Relish, TypeId, parse, to_vec};
+    use crate::{
+        Null, ParseError, ParseErrorKind, ParseResult, PathFrame, Relish, TypeId, parse,
+        parse_borrowed, to_vec,
+    };
     use bytes::Bytes;
 
     #[test]
@@ -595,21 +1695,23 @@ mod tests {
             (Ok(true), &[0x01u8, 0xFF]),
             (Ok(false), &[0x01, 0x00]),
             (
-                Err(ParseError::new(ParseErrorKind::InvalidBoolValue(0x42))),
+                Err(ParseError::new(ParseErrorKind::InvalidBoolValue(0x42)).with_offset(0)),
                 &[0x01, 0x42],
             ),
             (
                 Err(ParseError::new(ParseErrorKind::TypeMismatch {
                     expected: 0x01,
                     actual: 0x04,
-                })),
+                })
+                .with_offset(0)),
                 &[0x04, 0x2A, 0x00, 0x00, 0x00],
             ),
             (
                 Err(ParseError::new(ParseErrorKind::InsufficientData {
                     needed: 1,
                     available: 0,
-                })),
+                })
+                .with_offset(0)),
                 &[0x01],
             ),
         ]);
@@ -624,11 +1726,12 @@ mod tests {
                 Err(ParseError::new(ParseErrorKind::InsufficientData {
                     needed: 1,
                     available: 0,
-                })),
+                })
+                .with_offset(0)),
                 &[0x02],
             ),
             (
-                Err(ParseError::new(ParseErrorKind::InvalidTypeId(0x80))),
+                Err(ParseError::new(ParseErrorKind::InvalidTypeId(0x80)).with_offset(0)),
                 &[0x80],
             ),
         ]);
@@ -642,7 +1745,8 @@ mod tests {
                 Err(ParseError::new(ParseErrorKind::InsufficientData {
                     needed: 4,
                     available: 1,
-                })),
+                })
+                .with_offset(0)),
                 &[0x04, 0x2A],
             ),
         ]);
@@ -720,6 +1824,119 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_timestamp() {
+        assert_roundtrips(&[
+            (
+                Ok(time::OffsetDateTime::from_unix_timestamp(1234567890).unwrap()),
+                &[0x13u8, 0xD2, 0x02, 0x96, 0x49, 0x00, 0x00, 0x00, 0x00],
+            ),
+            (
+                Err(ParseError::new(ParseErrorKind::InvalidTimestamp(u64::MAX)).with_offset(0)),
+                &[0x13u8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
+            ),
+        ]);
+        assert_eq!(
+            to_vec(&time::OffsetDateTime::from_unix_timestamp(-1).unwrap())
+                .unwrap_err()
+                .kind(),
+            &crate::WriteErrorKind::InvalidTimestamp
+        );
+    }
+
+    #[cfg(feature = "jiff")]
+    #[test]
+    fn test_jiff_timestamp() {
+        assert_roundtrips(&[
+            (
+                Ok(jiff::Timestamp::from_second(1234567890).unwrap()),
+                &[0x13u8, 0xD2, 0x02, 0x96, 0x49, 0x00, 0x00, 0x00, 0x00],
+            ),
+            (
+                Err(ParseError::new(ParseErrorKind::InvalidTimestamp(u64::MAX)).with_offset(0)),
+                &[0x13u8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
+            ),
+        ]);
+        assert_eq!(
+            to_vec(&jiff::Timestamp::from_second(-1).unwrap())
+                .unwrap_err()
+                .kind(),
+            &crate::WriteErrorKind::InvalidTimestamp
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_uuid() {
+        let uuid = uuid::Uuid::from_u128(0x0102030405060708090A0B0C0D0E0F10);
+        assert_roundtrips(&[(
+            Ok(uuid),
+            &[
+                0x06u8, 0x10, 0x0F, 0x0E, 0x0D, 0x0C, 0x0B, 0x0A, 0x09, 0x08, 0x07, 0x06, 0x05,
+                0x04, 0x03, 0x02, 0x01,
+            ],
+        )]);
+    }
+
+    #[cfg(feature = "ordered-float")]
+    #[test]
+    fn test_ordered_float() {
+        assert_roundtrips(&[(
+            Ok(ordered_float::OrderedFloat(1.5f32)),
+            &[0x0Cu8, 0x00, 0x00, 0xC0, 0x3F],
+        )]);
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn test_decimal() {
+        assert_roundtrips(&[
+            (
+                Ok(rust_decimal::Decimal::new(150, 2)),
+                &[0x0Eu8, 0x08, b'1', b'.', b'5', b'0'],
+            ),
+            (
+                Err(ParseError::new(ParseErrorKind::InvalidFormat("decimal")).with_offset(0)),
+                &[0x0Eu8, 0x02, b'x'],
+            ),
+        ]);
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_url() {
+        assert_roundtrips(&[
+            (
+                Ok(url::Url::parse("http://a.io/").unwrap()),
+                &[
+                    0x0Eu8, 0x18, b'h', b't', b't', b'p', b':', b'/', b'/', b'a', b'.', b'i', b'o',
+                    b'/',
+                ],
+            ),
+            (
+                Err(ParseError::new(ParseErrorKind::InvalidFormat("URL")).with_offset(0)),
+                &[0x0Eu8, 0x02, b'x'],
+            ),
+        ]);
+    }
+
+    #[cfg(feature = "semver")]
+    #[test]
+    fn test_semver() {
+        assert_roundtrips(&[
+            (
+                Ok(semver::Version::new(1, 2, 3)),
+                &[0x0Eu8, 0x0A, b'1', b'.', b'2', b'.', b'3'],
+            ),
+            (
+                Err(
+                    ParseError::new(ParseErrorKind::InvalidFormat("semver version")).with_offset(0),
+                ),
+                &[0x0Eu8, 0x02, b'x'],
+            ),
+        ]);
+    }
     #[test]
     fn test_string() {
         assert_roundtrips(&[
@@ -731,7 +1948,7 @@ mod tests {
                 ],
             ),
             (
-                Err(ParseError::new(ParseErrorKind::InvalidUtf8)),
+                Err(ParseError::new(ParseErrorKind::InvalidUtf8).with_offset(0)),
                 &[0x0E, 0x08, 0xFF, 0xFE, 0xFD, 0xFC],
             ),
         ]);
@@ -750,7 +1967,7 @@ mod tests {
                 ],
             ),
             (
-                Err(ParseError::new(ParseErrorKind::InvalidUtf8)),
+                Err(ParseError::new(ParseErrorKind::InvalidUtf8).with_offset(0)),
                 &[0x0E, 0x08, 0xFF, 0xFE, 0xFD, 0xFC],
             ),
         ]);
@@ -772,7 +1989,7 @@ mod tests {
                 ],
             ),
             (
-                Err(ParseError::new(ParseErrorKind::InvalidUtf8)),
+                Err(ParseError::new(ParseErrorKind::InvalidUtf8).with_offset(0)),
                 &[0x0E, 0x08, 0xFF, 0xFE, 0xFD, 0xFC],
             ),
         ]);
@@ -783,6 +2000,46 @@ mod tests {
         assert_eq!(to_vec(&box_str).unwrap(), to_vec(&string_data).unwrap());
     }
 
+    #[test]
+    fn test_borrowed_str() {
+        use std::borrow::Cow;
+
+        let data = [0x0Eu8, 0x0A, b'H', b'e', b'l', b'l', b'o'];
+
+        let parsed = parse_borrowed::<&str>(&data).unwrap();
+        assert_eq!(parsed, "Hello");
+        assert_eq!(parsed.as_ptr(), data[2..].as_ptr());
+        assert_eq!(to_vec(&parsed).unwrap(), data);
+
+        let parsed = parse_borrowed::<Cow<'_, str>>(&data).unwrap();
+        assert!(matches!(parsed, Cow::Borrowed("Hello")));
+        assert_eq!(to_vec(&parsed).unwrap(), data);
+
+        assert_eq!(
+            parse_borrowed::<&str>(&[0x0E, 0x08, 0xFF, 0xFE, 0xFD, 0xFC]),
+            Err(ParseError::new(ParseErrorKind::InvalidUtf8).with_offset(0))
+        );
+    }
+
+    #[test]
+    fn test_borrowed_slice() {
+        let data = [0x0Fu8, 0x0A, 0x02, 0x01, 0x02, 0x03, 0x04];
+
+        let parsed = parse_borrowed::<&[u8]>(&data).unwrap();
+        assert_eq!(parsed, &[1, 2, 3, 4]);
+        assert_eq!(parsed.as_ptr(), data[3..].as_ptr());
+        assert_eq!(to_vec(&parsed).unwrap(), data);
+
+        assert_eq!(
+            parse_borrowed::<&[u8]>(&[0x0F, 0x0A, 0x04, 0x01, 0x00, 0x00, 0x00]),
+            Err(ParseError::new(ParseErrorKind::TypeMismatch {
+                expected: 0x02,
+                actual: 0x04,
+            })
+            .with_offset(0))
+        );
+    }
+
     #[test]
     fn test_vec() {
         assert_roundtrips(&[
@@ -892,7 +2149,8 @@ mod tests {
                 Err(ParseError::new(ParseErrorKind::TypeMismatch {
                     expected: 0x02,
                     actual: 0x04,
-                })),
+                })
+                .with_offset(0)),
                 &[0x0Fu8, 0x0A, 0x04, 0x01, 0x00, 0x00, 0x00],
             ),
         ]);
@@ -921,7 +2179,9 @@ mod tests {
             ),
             (Ok(map_empty), &[0x10, 0x04, 0x04, 0x04]),
             (
-                Err(ParseError::new(ParseErrorKind::DuplicateMapKey)),
+                Err(ParseError::new(ParseErrorKind::DuplicateMapKey)
+                    .with_offset(12)
+                    .with_frame(PathFrame::MapKey(1))),
                 &[
                     0x10u8, 0x24, 0x04, 0x04, 0x01, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x01,
                     0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00,
@@ -939,4 +2199,274 @@ mod tests {
             ],
         )]);
     }
+
+    #[test]
+    fn test_btree_map() {
+        use std::collections::BTreeMap;
+
+        let map = BTreeMap::from([(2u8, "b".to_string()), (1u8, "a".to_string())]);
+
+        // Entries are always written in key order
+        assert_roundtrips(&[
+            (
+                Ok(map),
+                &[0x10u8, 0x10, 0x02, 0x0E, 0x01, 0x02, b'a', 0x02, 0x02, b'b'],
+            ),
+            (
+                Err(ParseError::new(ParseErrorKind::DuplicateMapKey)
+                    .with_offset(7)
+                    .with_frame(PathFrame::MapKey(1))),
+                &[0x10u8, 0x10, 0x02, 0x0E, 0x01, 0x02, b'a', 0x01, 0x02, b'b'],
+            ),
+        ]);
+    }
+
+    #[cfg(feature = "indexmap")]
+    #[test]
+    fn test_index_map() {
+        let mut map = indexmap::IndexMap::<u8, u8>::new();
+        map.insert(2, 20);
+        map.insert(1, 10);
+
+        // Entries keep their insertion order
+        assert_roundtrips(&[(Ok(map), &[0x10u8, 0x0C, 0x02, 0x02, 0x02, 0x14, 0x01, 0x0A])]);
+    }
+
+    #[test]
+    #[cfg(feature = "hashbrown")]
+    fn test_hashbrown() {
+        assert_roundtrips(&[(
+            Ok(hashbrown::HashMap::<u8, u8>::from([(1, 10)])),
+            &[0x10u8, 0x08, 0x02, 0x02, 0x01, 0x0A],
+        )]);
+        assert_roundtrips(&[(
+            Ok(hashbrown::HashSet::<u8>::from([7])),
+            &[0x0Fu8, 0x04, 0x02, 0x07],
+        )]);
+    }
+
+    #[test]
+    fn test_vec_deque() {
+        use std::collections::VecDeque;
+
+        assert_roundtrips(&[
+            (
+                Ok(VecDeque::from([1u8, 2, 3])),
+                &[0x0Fu8, 0x08, 0x02, 0x01, 0x02, 0x03],
+            ),
+            (Ok(VecDeque::new()), &[0x0F, 0x02, 0x02]),
+        ]);
+    }
+
+    #[test]
+    fn test_sets() {
+        use std::collections::{BTreeSet, HashSet};
+
+        assert_roundtrips(&[
+            (
+                Ok(BTreeSet::from([3u8, 1, 2])),
+                &[0x0Fu8, 0x08, 0x02, 0x01, 0x02, 0x03],
+            ),
+            (
+                Err(ParseError::new(ParseErrorKind::DuplicateSetElement)
+                    .with_offset(5)
+                    .with_frame(PathFrame::Index(2))),
+                &[0x0Fu8, 0x08, 0x02, 0x01, 0x02, 0x01],
+            ),
+        ]);
+        assert_roundtrips(&[
+            (Ok(HashSet::from([7u8])), &[0x0Fu8, 0x04, 0x02, 0x07]),
+            (
+                Err(ParseError::new(ParseErrorKind::DuplicateSetElement)
+                    .with_offset(4)
+                    .with_frame(PathFrame::Index(1))),
+                &[0x0Fu8, 0x06, 0x02, 0x07, 0x07],
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_unit() {
+        assert_roundtrips(&[(Ok(()), &[0x00u8])]);
+    }
+
+    #[test]
+    fn test_tuple() {
+        assert_roundtrips(&[
+            (
+                Ok((1u8, "a".to_string())),
+                &[0x11u8, 0x0E, 0x00, 0x02, 0x01, 0x01, 0x0E, 0x02, b'a'],
+            ),
+            (
+                Err(ParseError::new(ParseErrorKind::MissingRequiredField)
+                    .with_offset(0)
+                    .with_frame(PathFrame::Index(1))),
+                &[0x11u8, 0x06, 0x00, 0x02, 0x01],
+            ),
+            (
+                Err(ParseError::new(ParseErrorKind::TypeMismatch {
+                    expected: TypeId::String as u8,
+                    actual: TypeId::U8 as u8,
+                })
+                .with_offset(6)
+                .with_frame(PathFrame::Index(1))),
+                &[0x11u8, 0x0C, 0x00, 0x02, 0x01, 0x01, 0x02, 0x02],
+            ),
+        ]);
+        assert_roundtrips(&[(
+            Ok((0u8, 1u8, 2u8, 3u8, 4u8, 5u8, 6u8, 7u8, 8u8, 9u8, 10u8, 11u8)),
+            &[
+                0x11u8, 0x48, 0x00, 0x02, 0x00, 0x01, 0x02, 0x01, 0x02, 0x02, 0x02, 0x03, 0x02,
+                0x03, 0x04, 0x02, 0x04, 0x05, 0x02, 0x05, 0x06, 0x02, 0x06, 0x07, 0x02, 0x07, 0x08,
+                0x02, 0x08, 0x09, 0x02, 0x09, 0x0A, 0x02, 0x0A, 0x0B, 0x02, 0x0B,
+            ],
+        )]);
+    }
+
+    #[test]
+    fn test_fixed_array() {
+        assert_roundtrips(&[
+            (Ok([1u8, 2, 3]), &[0x0Fu8, 0x08, 0x02, 0x01, 0x02, 0x03]),
+            (
+                Err(ParseError::new(ParseErrorKind::ArrayLengthMismatch {
+                    expected: 3,
+                    actual: 2,
+                })
+                .with_offset(0)),
+                &[0x0Fu8, 0x06, 0x02, 0x01, 0x02],
+            ),
+        ]);
+        assert_roundtrips(&[(Ok([0u32; 0]), &[0x0Fu8, 0x02, 0x04])]);
+    }
+
+    #[test]
+    fn test_pointers() {
+        use std::borrow::Cow;
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        let data: &[u8] = &[0x04, 0x2A, 0x00, 0x00, 0x00];
+        assert_roundtrips(&[(Ok(Box::new(42u32)), data)]);
+        assert_roundtrips(&[(Ok(Rc::new(42u32)), data)]);
+        assert_roundtrips(&[(Ok(Arc::new(42u32)), data)]);
+        assert_roundtrips(&[(Ok(Cow::<u32>::Owned(42)), data)]);
+        assert_eq!(to_vec(&Cow::Borrowed(&42u32)).unwrap(), data);
+
+        // Dynamically typed values keep their own type through the pointer
+        let value = Box::new(crate::Value::String("hi".to_string()));
+        assert_roundtrips(&[(Ok(value), &[0x0Eu8, 0x04, b'h', b'i'])]);
+    }
+
+    #[test]
+    fn test_result() {
+        assert_roundtrips::<Result<u8, String>>(&[
+            (Ok(Ok(7)), &[0x12u8, 0x06, 0x00, 0x02, 0x07]),
+            (
+                Ok(Err("no".to_string())),
+                &[0x12u8, 0x0A, 0x01, 0x0E, 0x04, b'n', b'o'],
+            ),
+            (
+                Err(ParseError::new(ParseErrorKind::UnknownVariant(2)).with_offset(0)),
+                &[0x12u8, 0x06, 0x02, 0x02, 0x07],
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_std_scalars() {
+        use std::marker::PhantomData;
+        use std::num::{NonZeroU16, Wrapping};
+
+        assert_roundtrips(&[(Ok(PhantomData::<str>), &[0x00u8])]);
+        assert_roundtrips(&[(Ok(Wrapping(7u8)), &[0x02u8, 0x07])]);
+        assert_roundtrips(&[
+            (Ok(NonZeroU16::new(258).unwrap()), &[0x03u8, 0x02, 0x01]),
+            (
+                Err(ParseError::new(ParseErrorKind::ZeroValue).with_offset(0)),
+                &[0x03u8, 0x00, 0x00],
+            ),
+        ]);
+        assert_roundtrips(&[
+            (Ok('é'), &[0x04u8, 0xE9, 0x00, 0x00, 0x00]),
+            (
+                Err(ParseError::new(ParseErrorKind::InvalidChar(0xD800)).with_offset(0)),
+                &[0x04u8, 0x00, 0xD8, 0x00, 0x00],
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_std_time() {
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        assert_roundtrips(&[
+            (
+                Ok(Duration::new(1, 500)),
+                &[
+                    0x11u8, 0x20, 0x00, 0x05, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+                    0x04, 0xF4, 0x01, 0x00, 0x00,
+                ],
+            ),
+            (
+                Err(
+                    ParseError::new(ParseErrorKind::InvalidDurationNanos(1_000_000_000))
+                        .with_offset(0),
+                ),
+                &[
+                    0x11u8, 0x20, 0x00, 0x05, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+                    0x04, 0x00, 0xCA, 0x9A, 0x3B,
+                ],
+            ),
+        ]);
+        assert_roundtrips(&[(
+            Ok(UNIX_EPOCH + Duration::from_secs(1234567890)),
+            &[0x13u8, 0xD2, 0x02, 0x96, 0x49, 0x00, 0x00, 0x00, 0x00],
+        )]);
+        assert_eq!(
+            to_vec(&(UNIX_EPOCH - Duration::from_secs(1)))
+                .unwrap_err()
+                .kind(),
+            &crate::WriteErrorKind::InvalidTimestamp
+        );
+        assert!(to_vec(&SystemTime::now()).is_ok());
+    }
+
+    #[test]
+    fn test_std_net() {
+        use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+        assert_roundtrips(&[(
+            Ok(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4))),
+            &[0x12u8, 0x0C, 0x00, 0x04, 0x04, 0x03, 0x02, 0x01],
+        )]);
+        assert_roundtrips(&[(
+            Ok(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+            &[
+                0x12u8, 0x24, 0x01, 0x06, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+        )]);
+        assert_roundtrips(&[(
+            Ok(SocketAddr::from(([127, 0, 0, 1], 80))),
+            &[
+                0x12u8, 0x1A, 0x00, 0x11, 0x14, 0x00, 0x04, 0x01, 0x00, 0x00, 0x7F, 0x01, 0x03,
+                0x50, 0x00,
+            ],
+        )]);
+        let addr: SocketAddr = "[::1]:8080".parse().unwrap();
+        assert_eq!(
+            parse::<SocketAddr>(Bytes::from(to_vec(&addr).unwrap())),
+            Ok(addr)
+        );
+    }
+
+    #[test]
+    fn test_path_buf() {
+        use std::path::PathBuf;
+
+        assert_roundtrips(&[(
+            Ok(PathBuf::from("/tmp")),
+            &[0x0Eu8, 0x08, b'/', b't', b'm', b'p'],
+        )]);
+    }
 }
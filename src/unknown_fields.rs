@@ -0,0 +1,99 @@
+use alloc::vec::Vec;
+
+use crate::write::Sink;
+use crate::{RawValue, Relish, WriteResult};
+
+/// Struct fields that a derived type does not know about, kept so that they
+/// can be written back out.
+///
+/// Add a field of this type marked `#[relish(unknown_fields)]` to a derived
+/// struct, and fields with IDs the struct does not declare are collected into
+/// it instead of being discarded. They are merged back in field ID order when
+/// the struct is written.
+///
+/// ```
+/// #[derive(relish::Relish)]
+/// struct Person {
+///     #[relish(field_id = 0)]
+///     name: String,
+///     #[relish(unknown_fields)]
+///     unknown: relish::UnknownFields,
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnknownFields {
+    /// Fields in increasing field ID order.
+    fields: Vec<(u8, RawValue)>,
+}
+
+impl UnknownFields {
+    /// Creates an empty set of unknown fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of unknown fields.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Returns whether there are no unknown fields.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Returns the unknown fields and their values, in field ID order.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &RawValue)> {
+        self.fields
+            .iter()
+            .map(|(field_id, value)| (*field_id, value))
+    }
+
+    /// Removes all unknown fields, so that they are not written.
+    pub fn clear(&mut self) {
+        self.fields.clear();
+    }
+
+    /// Adds a field, which must come after every field already added.
+    pub(crate) fn push(&mut self, field_id: u8, value: RawValue) {
+        self.fields.push((field_id, value));
+    }
+
+    fn between(&self, after: Option<u8>, before: Option<u8>) -> &[(u8, RawValue)] {
+        let start = match after {
+            Some(after) => self.fields.partition_point(|(id, _)| *id <= after),
+            None => 0,
+        };
+        let end = match before {
+            Some(before) => self.fields.partition_point(|(id, _)| *id < before),
+            None => self.fields.len(),
+        };
+        &self.fields[start..end.max(start)]
+    }
+
+    /// Returns the encoded length of the fields with IDs strictly between
+    /// `after` and `before`, where `None` leaves that end unbounded.
+    #[doc(hidden)]
+    pub fn fields_length_between(&self, after: Option<u8>, before: Option<u8>) -> usize {
+        self.between(after, before)
+            .iter()
+            .map(|(_, value)| 2 + value.value_length())
+            .sum()
+    }
+
+    /// Writes the fields with IDs strictly between `after` and `before`.
+    #[doc(hidden)]
+    pub fn write_fields_between<W: Sink + ?Sized>(
+        &self,
+        buffer: &mut W,
+        after: Option<u8>,
+        before: Option<u8>,
+    ) -> WriteResult<()> {
+        for (field_id, value) in self.between(after, before) {
+            buffer.write_byte(*field_id)?;
+            buffer.write_byte(value.value_type() as u8)?;
+            value.write_value(buffer)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,611 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem;
+
+use bytes::Bytes;
+
+use crate::buf::BytesRef;
+use crate::parse::{
+    check_not_zero_size, parse_type_and_value, parse_value_for_typeid, read_field_id,
+};
+use crate::types::{TypeLength, tagged_varint_length_size, write_entries};
+use crate::write::Sink;
+use crate::{
+    ParseError, ParseErrorKind, ParseResult, Relish, TypeId, WriteError, WriteErrorKind,
+    WriteResult,
+};
+
+/// A schemaless representation of any relish value.
+///
+/// This type can represent any valid relish value without requiring a schema
+/// or type information at compile time. Parsing a message as a `Value` and
+/// writing it back reproduces the original bytes.
+///
+/// Because a `Value` carries its type at runtime, its [`Relish::TYPE`] is
+/// only a placeholder ([`TypeId::Null`]); use [`Relish::value_type`] instead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Null value (no content).
+    Null,
+
+    /// Boolean value.
+    Bool(bool),
+
+    /// Unsigned 8-bit integer.
+    U8(u8),
+
+    /// Unsigned 16-bit integer.
+    U16(u16),
+
+    /// Unsigned 32-bit integer.
+    U32(u32),
+
+    /// Unsigned 64-bit integer.
+    U64(u64),
+
+    /// Unsigned 128-bit integer.
+    U128(u128),
+
+    /// Signed 8-bit integer.
+    I8(i8),
+
+    /// Signed 16-bit integer.
+    I16(i16),
+
+    /// Signed 32-bit integer.
+    I32(i32),
+
+    /// Signed 64-bit integer.
+    I64(i64),
+
+    /// Signed 128-bit integer.
+    I128(i128),
+
+    /// 32-bit floating point.
+    F32(f32),
+
+    /// 64-bit floating point.
+    F64(f64),
+
+    /// UTF-8 string.
+    String(String),
+
+    /// Homogeneous array of values.
+    Array {
+        /// The element type ID.
+        element_type: TypeId,
+        /// The array elements.
+        elements: Vec<Value>,
+    },
+
+    /// Map of key-value pairs.
+    Map {
+        /// The key type ID.
+        key_type: TypeId,
+        /// The value type ID.
+        value_type: TypeId,
+        /// The key-value pairs.
+        entries: Vec<(Value, Value)>,
+    },
+
+    /// Struct with numbered fields.
+    Struct {
+        /// The fields as (field_id, value) pairs.
+        fields: Vec<(u8, Value)>,
+    },
+
+    /// Enum variant.
+    Enum {
+        /// The variant ID.
+        variant_id: u8,
+        /// The variant value.
+        value: Box<Value>,
+    },
+
+    /// Unix timestamp (seconds since epoch).
+    Timestamp(u64),
+}
+
+impl Value {
+    /// Returns the length of the content following the length prefix, or
+    /// `None` for fixed-size types.
+    fn content_length(&self) -> Option<usize> {
+        match self {
+            Value::String(s) => Some(s.len()),
+            Value::Array { elements, .. } => {
+                Some(1 + elements.iter().map(Relish::value_length).sum::<usize>())
+            }
+            Value::Map { entries, .. } => Some(
+                2 + entries
+                    .iter()
+                    .map(|(k, v)| k.value_length() + v.value_length())
+                    .sum::<usize>(),
+            ),
+            Value::Struct { fields } => Some(
+                fields
+                    .iter()
+                    .map(|(_, v)| 2 + v.value_length())
+                    .sum::<usize>(),
+            ),
+            Value::Enum { value, .. } => Some(2 + value.value_length()),
+            _ => None,
+        }
+    }
+}
+
+fn check_field_id(field_id: u8) -> WriteResult<()> {
+    if field_id & 0x80 != 0 {
+        return Err(WriteError::new(WriteErrorKind::FieldIdTooLarge(field_id)));
+    }
+    Ok(())
+}
+
+/// Checks that no two keys have the same encoding, since they would be
+/// rejected as duplicates when parsed.
+fn check_unique_keys(entries: &[(Value, Value)]) -> WriteResult<()> {
+    let mut seen_keys = BTreeSet::new();
+    for (key, _) in entries {
+        let mut encoded = Vec::new();
+        key.write_value(&mut encoded)?;
+        if !seen_keys.insert(encoded) {
+            return Err(WriteError::new(WriteErrorKind::DuplicateMapKey));
+        }
+    }
+    Ok(())
+}
+
+fn check_element_type(expected: TypeId, value: &Value) -> WriteResult<()> {
+    let actual = value.value_type();
+    if actual != expected {
+        return Err(WriteError::new(WriteErrorKind::MixedElementTypes {
+            expected: expected as u8,
+            actual: actual as u8,
+        }));
+    }
+    Ok(())
+}
+
+impl Relish for Value {
+    const TYPE: TypeId = TypeId::Null;
+
+    /// `TYPE` is only a placeholder, so a `Value` can only be parsed through
+    /// [`parse_typed_value`](Relish::parse_typed_value).
+    fn parse_value(_data: &mut BytesRef) -> ParseResult<Self> {
+        Err(ParseError::new(ParseErrorKind::MissingTypeId))
+    }
+
+    fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> WriteResult<()> {
+        match self {
+            Value::Null => Ok(()),
+            Value::Bool(v) => v.write_value(buffer),
+            Value::U8(v) => v.write_value(buffer),
+            Value::U16(v) => v.write_value(buffer),
+            Value::U32(v) => v.write_value(buffer),
+            Value::U64(v) => v.write_value(buffer),
+            Value::U128(v) => v.write_value(buffer),
+            Value::I8(v) => v.write_value(buffer),
+            Value::I16(v) => v.write_value(buffer),
+            Value::I32(v) => v.write_value(buffer),
+            Value::I64(v) => v.write_value(buffer),
+            Value::I128(v) => v.write_value(buffer),
+            Value::F32(v) => v.write_value(buffer),
+            Value::F64(v) => v.write_value(buffer),
+            Value::Timestamp(v) => v.write_value(buffer),
+            Value::String(v) => v.write_value(buffer),
+            Value::Array {
+                element_type,
+                elements,
+            } => {
+                for elem in elements {
+                    check_element_type(*element_type, elem)?;
+                }
+
+                buffer.write_varsize(
+                    || self.content_length().unwrap(),
+                    |buffer| {
+                        buffer.write_byte(*element_type as u8)?;
+                        for elem in elements {
+                            elem.write_value(buffer)?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+            Value::Map {
+                key_type,
+                value_type,
+                entries,
+            } => {
+                for (k, v) in entries {
+                    check_element_type(*key_type, k)?;
+                    check_element_type(*value_type, v)?;
+                }
+                // Canonical sinks compare the sorted keys as they write them
+                if !buffer.is_canonical() {
+                    check_unique_keys(entries)?;
+                }
+
+                buffer.write_varsize(
+                    || self.content_length().unwrap(),
+                    |buffer| {
+                        buffer.write_byte(*key_type as u8)?;
+                        buffer.write_byte(*value_type as u8)?;
+                        write_entries(entries.iter().map(|(k, v)| (k, v)), buffer)
+                    },
+                )
+            }
+            Value::Struct { fields } => {
+                let mut previous = None;
+                for &(field_id, _) in fields {
+                    check_field_id(field_id)?;
+                    if let Some(previous) = previous
+                        && field_id <= previous
+                    {
+                        return Err(WriteError::new(WriteErrorKind::FieldOrderViolation {
+                            previous,
+                            current: field_id,
+                        }));
+                    }
+                    previous = Some(field_id);
+                }
+
+                buffer.write_varsize(
+                    || self.content_length().unwrap(),
+                    |buffer| {
+                        for (field_id, value) in fields {
+                            buffer.write_byte(*field_id)?;
+                            buffer.write_byte(value.value_type() as u8)?;
+                            value.write_value(buffer)?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+            Value::Enum { variant_id, value } => {
+                check_field_id(*variant_id)?;
+
+                buffer.write_varsize(
+                    || self.content_length().unwrap(),
+                    |buffer| {
+                        buffer.write_byte(*variant_id)?;
+                        buffer.write_byte(value.value_type() as u8)?;
+                        value.write_value(buffer)
+                    },
+                )
+            }
+        }
+    }
+
+    fn value_length(&self) -> usize {
+        match self.content_length() {
+            Some(len) => tagged_varint_length_size(len) + len,
+            None => match self.value_type().length() {
+                TypeLength::Fixed(size) => size,
+                TypeLength::Varsize => unreachable!(),
+            },
+        }
+    }
+
+    fn value_type(&self) -> TypeId {
+        match self {
+            Value::Null => TypeId::Null,
+            Value::Bool(_) => TypeId::Bool,
+            Value::U8(_) => TypeId::U8,
+            Value::U16(_) => TypeId::U16,
+            Value::U32(_) => TypeId::U32,
+            Value::U64(_) => TypeId::U64,
+            Value::U128(_) => TypeId::U128,
+            Value::I8(_) => TypeId::I8,
+            Value::I16(_) => TypeId::I16,
+            Value::I32(_) => TypeId::I32,
+            Value::I64(_) => TypeId::I64,
+            Value::I128(_) => TypeId::I128,
+            Value::F32(_) => TypeId::F32,
+            Value::F64(_) => TypeId::F64,
+            Value::String(_) => TypeId::String,
+            Value::Array { .. } => TypeId::Array,
+            Value::Map { .. } => TypeId::Map,
+            Value::Struct { .. } => TypeId::Struct,
+            Value::Enum { .. } => TypeId::Enum,
+            Value::Timestamp(_) => TypeId::Timestamp,
+        }
+    }
+
+    fn accepts_type(_type_id: TypeId) -> bool {
+        true
+    }
+
+    fn parse_typed_value(type_id: TypeId, data: &mut BytesRef) -> ParseResult<Self> {
+        match type_id {
+            TypeId::Null => Ok(Value::Null),
+            TypeId::Bool => Ok(Value::Bool(bool::parse_value(data)?)),
+            TypeId::U8 => Ok(Value::U8(u8::parse_value(data)?)),
+            TypeId::U16 => Ok(Value::U16(u16::parse_value(data)?)),
+            TypeId::U32 => Ok(Value::U32(u32::parse_value(data)?)),
+            TypeId::U64 => Ok(Value::U64(u64::parse_value(data)?)),
+            TypeId::U128 => Ok(Value::U128(u128::parse_value(data)?)),
+            TypeId::I8 => Ok(Value::I8(i8::parse_value(data)?)),
+            TypeId::I16 => Ok(Value::I16(i16::parse_value(data)?)),
+            TypeId::I32 => Ok(Value::I32(i32::parse_value(data)?)),
+            TypeId::I64 => Ok(Value::I64(i64::parse_value(data)?)),
+            TypeId::I128 => Ok(Value::I128(i128::parse_value(data)?)),
+            TypeId::F32 => Ok(Value::F32(f32::parse_value(data)?)),
+            TypeId::F64 => Ok(Value::F64(f64::parse_value(data)?)),
+            TypeId::Timestamp => Ok(Value::Timestamp(u64::parse_value(data)?)),
+            TypeId::String => Ok(Value::String(String::parse_value(data)?)),
+
+            TypeId::Array => {
+                let element_type = TypeId::read_for_type::<Value>(data)?;
+
+                let mut elements = Vec::new();
+                while !data.is_empty() {
+                    check_not_zero_size(data, &[element_type])?;
+                    data.add_element::<Value>(elements.len())?;
+                    elements.push(parse_value_for_typeid(data, element_type)?);
+                }
+                Ok(Value::Array {
+                    element_type,
+                    elements,
+                })
+            }
+
+            TypeId::Map => {
+                let key_type = TypeId::read_for_type::<Value>(data)?;
+                let value_type = TypeId::read_for_type::<Value>(data)?;
+
+                // Keys are compared by their encoding, since `Value` itself
+                // has no total equality.
+                let mut seen_keys = BTreeSet::new();
+                let mut entries = Vec::new();
+                while !data.is_empty() {
+                    check_not_zero_size(data, &[key_type, value_type])?;
+                    data.add_element::<(Value, Value)>(entries.len())?;
+                    let offset = data.offset();
+                    let key_bytes = data.as_slice();
+                    let key: Value = parse_value_for_typeid(data, key_type)?;
+                    let key_bytes = &key_bytes[..key_bytes.len() - data.len()];
+                    if !seen_keys.insert(key_bytes) {
+                        return Err(
+                            ParseError::new(ParseErrorKind::DuplicateMapKey).with_offset(offset)
+                        );
+                    }
+                    let value = parse_value_for_typeid(data, value_type)?;
+                    entries.push((key, value));
+                }
+                Ok(Value::Map {
+                    key_type,
+                    value_type,
+                    entries,
+                })
+            }
+
+            TypeId::Struct => {
+                let mut fields: Vec<(u8, Value)> = Vec::new();
+                while !data.is_empty() {
+                    let offset = data.offset();
+                    let field_id = read_field_id(data)?;
+                    if let Some(&(previous, _)) = fields.last()
+                        && field_id <= previous
+                    {
+                        return Err(ParseError::new(ParseErrorKind::FieldOrderViolation {
+                            previous,
+                            current: field_id,
+                        })
+                        .with_offset(offset));
+                    }
+                    data.allocate(mem::size_of::<(u8, Value)>())?;
+                    fields.push((field_id, parse_type_and_value(data)?));
+                }
+                Ok(Value::Struct { fields })
+            }
+
+            TypeId::Enum => {
+                let variant_id = read_field_id(data)?;
+                data.allocate(mem::size_of::<Value>())?;
+                let value = parse_type_and_value(data)?;
+                if !data.is_empty() {
+                    return Err(ParseError::new(ParseErrorKind::ExtraData {
+                        bytes_remaining: data.len(),
+                    })
+                    .with_offset(data.offset()));
+                }
+                Ok(Value::Enum {
+                    variant_id,
+                    value: Box::new(value),
+                })
+            }
+        }
+    }
+}
+
+/// Convert a value into a schemaless [`Value`].
+///
+/// This is equivalent to serializing `value` and parsing the result as a
+/// [`Value`].
+pub fn to_value<T: Relish>(value: &T) -> WriteResult<Value> {
+    let data = crate::to_vec(value)?;
+    crate::parse(Bytes::from(data))
+        .map_err(|e| WriteError::new(WriteErrorKind::InvalidEncoding(Box::new(e.kind().clone()))))
+}
+
+/// Convert a schemaless [`Value`] into `T`.
+///
+/// This is equivalent to serializing `value` and parsing the result as `T`,
+/// so any value that parses from the same bytes is accepted.
+pub fn from_value<T: Relish>(value: Value) -> ParseResult<T> {
+    let data = crate::to_vec(&value)
+        .map_err(|e| ParseError::new(ParseErrorKind::InvalidValue(e.kind().clone())))?;
+    crate::parse(Bytes::from(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+
+    use super::{Value, from_value, to_value};
+    use crate::buf::BytesRef;
+    use crate::{
+        ParseError, ParseErrorKind, Relish, TypeId, WriteError, WriteErrorKind, parse, to_vec,
+    };
+
+    #[test]
+    fn test_value_roundtrip() {
+        let cases: &[&[u8]] = &[
+            &[0x00],
+            &[0x01, 0xFF],
+            &[0x04, 0x2A, 0x00, 0x00, 0x00],
+            &[0x0E, 0x0A, b'H', b'e', b'l', b'l', b'o'],
+            &[0x0F, 0x0A, 0x03, 0x01, 0x00, 0x02, 0x00],
+            &[0x10, 0x10, 0x0E, 0x02, 0x02, b'a', 0x01, 0x02, b'b', 0x02],
+            &[0x11, 0x10, 0x00, 0x04, 0x2A, 0x00, 0x00, 0x00, 0x03, 0x00],
+            &[0x12, 0x0A, 0x01, 0x0E, 0x04, b'h', b'i'],
+            &[0x13, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        ];
+        for data in cases {
+            let value = parse::<Value>(Bytes::from(data.to_vec())).unwrap();
+            assert_eq!(to_vec(&value).unwrap(), *data);
+        }
+    }
+
+    #[test]
+    fn test_value_parse_errors() {
+        let cases: &[(&[u8], ParseErrorKind, usize)] = &[
+            (&[0x14], ParseErrorKind::InvalidTypeId(0x14), 0),
+            (
+                &[0x11, 0x08, 0x01, 0x00, 0x00, 0x00],
+                ParseErrorKind::FieldOrderViolation {
+                    previous: 1,
+                    current: 0,
+                },
+                4,
+            ),
+            (
+                &[0x10, 0x08, 0x02, 0x00, 0x01, 0x01],
+                ParseErrorKind::DuplicateMapKey,
+                5,
+            ),
+            (
+                &[0x0F, 0x0A, 0x0E, 0x02, b'a', 0x02, 0xFF],
+                ParseErrorKind::InvalidUtf8,
+                5,
+            ),
+        ];
+        for (data, kind, offset) in cases {
+            assert_eq!(
+                parse::<Value>(Bytes::from(data.to_vec())),
+                Err(ParseError::new(kind.clone()).with_offset(*offset))
+            );
+        }
+
+        // Without a type ID there is nothing to tell the value's type
+        let data = Bytes::from_static(&[0x2A]);
+        assert_eq!(
+            <Value as Relish>::parse_value(&mut BytesRef::new(&data)),
+            Err(ParseError::new(ParseErrorKind::MissingTypeId))
+        );
+    }
+
+    #[test]
+    fn test_value_write_errors() {
+        let mixed = Value::Array {
+            element_type: TypeId::U8,
+            elements: vec![Value::U8(1), Value::U16(2)],
+        };
+        assert_eq!(
+            to_vec(&mixed),
+            Err(WriteError::new(WriteErrorKind::MixedElementTypes {
+                expected: 0x02,
+                actual: 0x03,
+            }))
+        );
+
+        let elements = vec![Value::U8(1), Value::U16(2)];
+        assert_eq!(
+            to_vec(&elements),
+            Err(WriteError::new(WriteErrorKind::MixedElementTypes {
+                expected: 0x02,
+                actual: 0x03,
+            }))
+        );
+
+        let large_field = Value::Struct {
+            fields: vec![(0x80, Value::Null)],
+        };
+        assert_eq!(
+            from_value::<Value>(large_field),
+            Err(ParseError::new(ParseErrorKind::InvalidValue(
+                WriteErrorKind::FieldIdTooLarge(0x80)
+            )))
+        );
+
+        // Anything written must parse again
+        let unsorted = Value::Struct {
+            fields: vec![(1, Value::Null), (1, Value::Null)],
+        };
+        assert_eq!(
+            to_vec(&unsorted),
+            Err(WriteError::new(WriteErrorKind::FieldOrderViolation {
+                previous: 1,
+                current: 1
+            }))
+        );
+        let duplicate_keys = Value::Map {
+            key_type: TypeId::U8,
+            value_type: TypeId::Null,
+            entries: vec![(Value::U8(1), Value::Null), (Value::U8(1), Value::Null)],
+        };
+        assert_eq!(
+            to_vec(&duplicate_keys),
+            Err(WriteError::new(WriteErrorKind::DuplicateMapKey))
+        );
+        assert_eq!(
+            crate::to_vec_canonical(&duplicate_keys),
+            Err(WriteError::new(WriteErrorKind::DuplicateMapKey))
+        );
+    }
+
+    #[test]
+    fn test_to_from_value() {
+        let mut map = HashMap::new();
+        map.insert("a".to_string(), vec![1u32, 2]);
+
+        let value = to_value(&map).unwrap();
+        assert_eq!(
+            value,
+            Value::Map {
+                key_type: TypeId::String,
+                value_type: TypeId::Array,
+                entries: vec![(
+                    Value::String("a".to_string()),
+                    Value::Array {
+                        element_type: TypeId::U32,
+                        elements: vec![Value::U32(1), Value::U32(2)],
+                    }
+                )],
+            }
+        );
+        assert_eq!(from_value::<HashMap<String, Vec<u32>>>(value), Ok(map));
+
+        assert_eq!(
+            from_value::<u32>(Value::String("a".to_string())),
+            Err(ParseError::new(ParseErrorKind::TypeMismatch {
+                expected: 0x04,
+                actual: 0x0E,
+            })
+            .with_offset(0))
+        );
+    }
+
+    #[test]
+    fn test_value_field() {
+        let value = Value::Struct {
+            fields: vec![(0, Value::U8(1)), (2, Value::String("x".to_string()))],
+        };
+        let data = to_vec(&vec![value.clone()]).unwrap();
+        assert_eq!(parse::<Vec<Value>>(Bytes::from(data)).unwrap(), vec![value]);
+    }
+}
@@ -1,12 +1,779 @@
-use crate::{Relish, WriteResult};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+
+use bytes::{BufMut, BytesMut};
+
+use crate::{
+    BorrowedFieldValue, RelishBorrow, WriteError, WriteErrorKind, WriteResult,
+    tagged_varint_length_size, write_tagged_varint_length,
+};
+
+/// A destination that serialized Relish data is written to.
+///
+/// [`Relish::write_value`](crate::Relish::write_value) is generic over the
+/// sink, so the same implementation can fill a `Vec<u8>` or a reused
+/// [`BytesMut`], or stream directly into an [`io::Write`] via [`to_writer`]
+/// or a [`BufMut`] via [`to_buf_mut`].
+pub trait Sink {
+    /// Writes a single byte.
+    fn write_byte(&mut self, byte: u8) -> WriteResult<()>;
+
+    /// Writes all of the given bytes.
+    fn write_bytes(&mut self, bytes: &[u8]) -> WriteResult<()>;
+
+    /// Hints that at least `additional` more bytes are about to be written.
+    fn reserve(&mut self, additional: usize) {
+        let _ = additional;
+    }
+
+    /// Returns whether values must be written in canonical form, with map
+    /// entries sorted by their encoded keys. See
+    /// [`to_vec_canonical`](crate::to_vec_canonical).
+    fn is_canonical(&self) -> bool {
+        false
+    }
+
+    /// Writes a tagged varint length followed by the content written by
+    /// `write_content`.
+    ///
+    /// `content_len` must return the number of bytes `write_content` writes.
+    /// By default it is called so that the length can be written first, which
+    /// means nested values are measured again at every level. Sinks that can
+    /// fill in the length afterwards should override this and never call it.
+    fn write_varsize(
+        &mut self,
+        content_len: impl FnOnce() -> usize,
+        write_content: impl FnOnce(&mut Self) -> WriteResult<()>,
+    ) -> WriteResult<()> {
+        write_tagged_varint_length(self, content_len())?;
+        write_content(self)
+    }
+}
+
+impl Sink for Vec<u8> {
+    fn write_byte(&mut self, byte: u8) -> WriteResult<()> {
+        self.push(byte);
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> WriteResult<()> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
+    }
+
+    fn write_varsize(
+        &mut self,
+        _content_len: impl FnOnce() -> usize,
+        write_content: impl FnOnce(&mut Self) -> WriteResult<()>,
+    ) -> WriteResult<()> {
+        let start = self.len();
+        self.extend_from_slice(&[0; 4]);
+        write_content(self)?;
+        let unused = patch_varsize(&mut self[start..])?;
+        self.truncate(self.len() - unused);
+        Ok(())
+    }
+}
+
+impl Sink for BytesMut {
+    fn write_byte(&mut self, byte: u8) -> WriteResult<()> {
+        self.put_u8(byte);
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> WriteResult<()> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        BytesMut::reserve(self, additional);
+    }
+
+    fn write_varsize(
+        &mut self,
+        _content_len: impl FnOnce() -> usize,
+        write_content: impl FnOnce(&mut Self) -> WriteResult<()>,
+    ) -> WriteResult<()> {
+        let start = self.len();
+        self.extend_from_slice(&[0; 4]);
+        write_content(self)?;
+        let unused = patch_varsize(&mut self[start..])?;
+        self.truncate(self.len() - unused);
+        Ok(())
+    }
+}
+
+/// Fills in the 4 bytes reserved for a length prefix at the start of `data`,
+/// which are followed by the content, and returns how many bytes `data` must
+/// be truncated by.
+///
+/// Content shorter than 128 bytes is moved back so that the prefix takes a
+/// single byte. Since only short content is ever moved, writing stays linear
+/// in the output size.
+pub(crate) fn patch_varsize(data: &mut [u8]) -> WriteResult<usize> {
+    let length = data.len() - 4;
+    if length < 128 {
+        data[0] = (length << 1) as u8;
+        data.copy_within(4.., 1);
+        Ok(3)
+    } else {
+        if length > (1 << 31) - 1 {
+            return Err(WriteError::new(WriteErrorKind::ContentTooLarge(length)));
+        }
+        let tagged_value = ((length as u32) << 1) | 1;
+        data[..4].copy_from_slice(&tagged_value.to_le_bytes());
+        Ok(0)
+    }
+}
+
+/// Adapts an [`io::Write`] into a [`Sink`].
+#[cfg(feature = "std")]
+struct IoSink<W> {
+    inner: W,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> Sink for IoSink<W> {
+    fn write_byte(&mut self, byte: u8) -> WriteResult<()> {
+        self.write_bytes(&[byte])
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> WriteResult<()> {
+        self.inner
+            .write_all(bytes)
+            .map_err(|e| WriteError::new(WriteErrorKind::Io(e.kind())))
+    }
+}
+
+/// Measures a value in a single pass, recording the content length of every
+/// varsize value in the order they are written.
+struct LengthSink {
+    written: usize,
+    lengths: Vec<usize>,
+}
+
+impl Sink for LengthSink {
+    fn write_byte(&mut self, _byte: u8) -> WriteResult<()> {
+        self.written += 1;
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> WriteResult<()> {
+        self.written += bytes.len();
+        Ok(())
+    }
+
+    fn write_varsize(
+        &mut self,
+        _content_len: impl FnOnce() -> usize,
+        write_content: impl FnOnce(&mut Self) -> WriteResult<()>,
+    ) -> WriteResult<()> {
+        let index = self.lengths.len();
+        self.lengths.push(0);
+        let start = self.written;
+        write_content(self)?;
+        let length = self.written - start;
+        self.lengths[index] = length;
+        self.written += tagged_varint_length_size(length);
+        Ok(())
+    }
+}
+
+/// Adapts a [`BufMut`] into a [`Sink`], writing the lengths measured by a
+/// [`LengthSink`].
+struct BufMutSink<'b, B> {
+    inner: &'b mut B,
+    written: usize,
+    lengths: alloc::vec::IntoIter<usize>,
+}
+
+impl<B: BufMut> Sink for BufMutSink<'_, B> {
+    fn write_byte(&mut self, byte: u8) -> WriteResult<()> {
+        self.write_bytes(&[byte])
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> WriteResult<()> {
+        let available = self.inner.remaining_mut();
+        if bytes.len() > available {
+            return Err(WriteError::new(WriteErrorKind::BufferTooSmall {
+                needed: bytes.len(),
+                available,
+            }));
+        }
+        self.inner.put_slice(bytes);
+        self.written += bytes.len();
+        Ok(())
+    }
+
+    fn write_varsize(
+        &mut self,
+        _content_len: impl FnOnce() -> usize,
+        write_content: impl FnOnce(&mut Self) -> WriteResult<()>,
+    ) -> WriteResult<()> {
+        let Some(length) = self.lengths.next() else {
+            return Err(WriteError::new(WriteErrorKind::InconsistentValue));
+        };
+        write_tagged_varint_length(self, length)?;
+        let start = self.written;
+        write_content(self)?;
+        if self.written - start != length {
+            return Err(WriteError::new(WriteErrorKind::InconsistentValue));
+        }
+        Ok(())
+    }
+}
+
+/// Adapts a `&mut [u8]` into a [`Sink`].
+///
+/// With `backpatch` set, lengths are filled in after the content as for
+/// `Vec<u8>`. That briefly needs up to 3 bytes per level of nesting beyond
+/// the final length, so otherwise each length is measured before it is
+/// written.
+struct SliceSink<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+    backpatch: bool,
+}
+
+impl Sink for SliceSink<'_> {
+    fn write_byte(&mut self, byte: u8) -> WriteResult<()> {
+        self.write_bytes(&[byte])
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> WriteResult<()> {
+        let available = self.buf.len() - self.len;
+        if bytes.len() > available {
+            return Err(WriteError::new(WriteErrorKind::BufferTooSmall {
+                needed: bytes.len(),
+                available,
+            }));
+        }
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    fn write_varsize(
+        &mut self,
+        content_len: impl FnOnce() -> usize,
+        write_content: impl FnOnce(&mut Self) -> WriteResult<()>,
+    ) -> WriteResult<()> {
+        if !self.backpatch {
+            write_tagged_varint_length(self, content_len())?;
+            return write_content(self);
+        }
+        let start = self.len;
+        self.write_bytes(&[0; 4])?;
+        write_content(self)?;
+        let unused = patch_varsize(&mut self.buf[start..self.len])?;
+        self.len -= unused;
+        Ok(())
+    }
+}
 
 /// Serialize a value to Relish binary format as a Vec<u8>.
-pub fn to_vec<T: Relish>(value: &T) -> WriteResult<Vec<u8>> {
+///
+/// This accepts any [`RelishBorrow`] type, which includes every
+/// [`Relish`](crate::Relish) type as well as types that borrow from a buffer.
+pub fn to_vec<'a, T: RelishBorrow<'a>>(value: &T) -> WriteResult<Vec<u8>> {
     let mut buffer = Vec::new();
 
-    buffer.push(T::TYPE as u8);
+    buffer.push(value.value_type() as u8);
 
     value.write_value(&mut buffer)?;
 
     Ok(buffer)
 }
+
+/// Serialize a value to Relish binary format, streaming it into `writer`.
+///
+/// Length prefixes are computed up front, so the message is never buffered in
+/// full, but unlike [`to_vec`] the content of nested values is measured again
+/// at each level of nesting. Many small writes are issued, so unbuffered
+/// writers such as files and sockets should be wrapped in an
+/// [`io::BufWriter`].
+#[cfg(feature = "std")]
+pub fn to_writer<'a, T: RelishBorrow<'a>, W: io::Write>(writer: W, value: &T) -> WriteResult<()> {
+    let mut sink = IoSink { inner: writer };
+
+    sink.write_byte(value.value_type() as u8)?;
+
+    value.write_value(&mut sink)
+}
+
+/// Serialize a value to Relish binary format, appending it to `buf`.
+///
+/// The value is measured in one pass before anything is written, and if
+/// `buf` cannot hold that many more bytes this fails with
+/// [`WriteErrorKind::BufferTooSmall`] and leaves `buf` unchanged. A value
+/// that writes different content the second time fails with
+/// [`WriteErrorKind::InconsistentValue`].
+pub fn to_buf_mut<'a, T: RelishBorrow<'a>, B: BufMut>(value: &T, buf: &mut B) -> WriteResult<()> {
+    let mut lengths = LengthSink {
+        written: 1,
+        lengths: Vec::new(),
+    };
+    value.write_value(&mut lengths)?;
+
+    let needed = lengths.written;
+    let available = buf.remaining_mut();
+    if needed > available {
+        return Err(WriteError::new(WriteErrorKind::BufferTooSmall {
+            needed,
+            available,
+        }));
+    }
+
+    let mut sink = BufMutSink {
+        inner: buf,
+        written: 0,
+        lengths: lengths.lengths.into_iter(),
+    };
+
+    sink.write_byte(value.value_type() as u8)?;
+
+    value.write_value(&mut sink)?;
+    if sink.lengths.next().is_some() {
+        return Err(WriteError::new(WriteErrorKind::InconsistentValue));
+    }
+    Ok(())
+}
+
+/// Serialize a value to Relish binary format into the start of `buf`,
+/// returning the number of bytes written.
+///
+/// Nothing is allocated. If the value does not fit, this fails with
+/// [`WriteErrorKind::BufferTooSmall`] before anything is written. Length
+/// prefixes are filled in after their content, unless `buf` has only a few
+/// bytes to spare, in which case nested values are measured again at each
+/// level of nesting as with [`to_writer`].
+pub fn to_slice<'a, T: RelishBorrow<'a>>(value: &T, buf: &mut [u8]) -> WriteResult<usize> {
+    let needed = 1 + value.value_length();
+    let available = buf.len();
+    if needed > available {
+        return Err(WriteError::new(WriteErrorKind::BufferTooSmall {
+            needed,
+            available,
+        }));
+    }
+
+    let mut sink = SliceSink {
+        buf,
+        len: 0,
+        backpatch: true,
+    };
+    sink.write_byte(value.value_type() as u8)?;
+    match value.write_value(&mut sink) {
+        Ok(()) => return Ok(sink.len),
+        Err(e) if matches!(e.kind(), WriteErrorKind::BufferTooSmall { .. }) => {}
+        Err(e) => return Err(e),
+    }
+
+    // The value fits, but not with room for the reserved length prefixes
+    sink.len = 1;
+    sink.backpatch = false;
+    value.write_value(&mut sink)?;
+    Ok(sink.len)
+}
+
+/// Writes the fields of a struct, for implementing
+/// [`Relish::write_value`](crate::Relish::write_value) and
+/// [`Relish::value_length`](crate::Relish::value_length) by hand.
+///
+/// The fields are written by a closure that calls [`field`](Self::field) for
+/// each one, in strictly increasing field ID order. The same closure is used
+/// to measure the content, so the length prefix always matches what is
+/// written; writing the fields from a method that is generic over the sink
+/// lets `write_value` and `value_length` share it.
+///
+/// ```
+/// use bytes::Bytes;
+/// use relish::{
+///     BytesRef, ParseError, ParseErrorKind, ParseResult, Relish, Sink, StructReader,
+///     StructWriter, TypeId, WriteResult,
+/// };
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Point {
+///     x: i32,
+///     label: Option<String>,
+/// }
+///
+/// impl Point {
+///     fn write_fields<W: Sink + ?Sized>(&self, writer: &mut StructWriter<'_, W>) -> WriteResult<()> {
+///         writer.field(0, &self.x)?;
+///         writer.field(1, &self.label)
+///     }
+/// }
+///
+/// impl Relish for Point {
+///     const TYPE: TypeId = TypeId::Struct;
+///
+///     fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+///         let mut reader = StructReader::new(data);
+///         let x = reader
+///             .field(0)?
+///             .ok_or_else(|| ParseError::new(ParseErrorKind::MissingRequiredField))?;
+///         let label = reader.field(1)?;
+///         reader.finish()?;
+///         Ok(Point { x, label })
+///     }
+///
+///     fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> WriteResult<()> {
+///         StructWriter::write(buffer, |writer| self.write_fields(writer))
+///     }
+///
+///     fn value_length(&self) -> usize {
+///         StructWriter::value_length(|writer| self.write_fields(writer))
+///     }
+/// }
+///
+/// let point = Point { x: 3, label: None };
+/// let data = relish::to_vec(&point).unwrap();
+/// assert_eq!(relish::parse::<Point>(Bytes::from(data)).unwrap(), point);
+/// ```
+pub struct StructWriter<'w, W: ?Sized = Vec<u8>> {
+    /// Where fields are written, or `None` if they are only being measured.
+    buffer: Option<&'w mut W>,
+    /// The number of bytes measured, if fields are only being measured.
+    content_len: usize,
+    last_field_id: Option<u8>,
+}
+
+impl<'w, W: Sink + ?Sized> StructWriter<'w, W> {
+    fn new(buffer: Option<&'w mut W>) -> Self {
+        StructWriter {
+            buffer,
+            content_len: 0,
+            last_field_id: None,
+        }
+    }
+
+    /// Writes a struct whose fields are written by `fields`, preceded by its
+    /// length prefix.
+    pub fn write(
+        buffer: &mut W,
+        fields: impl Fn(&mut StructWriter<'_, W>) -> WriteResult<()>,
+    ) -> WriteResult<()> {
+        buffer.write_varsize(
+            || StructWriter::content_length(&fields),
+            |buffer| fields(&mut StructWriter::new(Some(buffer))),
+        )
+    }
+
+    fn content_length(fields: impl FnOnce(&mut StructWriter<'_, W>) -> WriteResult<()>) -> usize {
+        let mut writer = StructWriter::new(None);
+        // Invalid fields are reported when they are written.
+        let _ = fields(&mut writer);
+        writer.content_len
+    }
+
+    /// Writes the field `field_id` with `value`, or nothing if `value` is
+    /// `None`.
+    ///
+    /// Fails with [`WriteErrorKind::FieldIdTooLarge`] if `field_id` has its
+    /// top bit set, and with [`WriteErrorKind::FieldOrderViolation`] unless it
+    /// is greater than every field ID before it, whether or not those fields
+    /// were written.
+    pub fn field<'a, F: BorrowedFieldValue<'a>>(
+        &mut self,
+        field_id: u8,
+        value: &F,
+    ) -> WriteResult<()> {
+        if field_id & 0x80 != 0 {
+            return Err(WriteError::new(WriteErrorKind::FieldIdTooLarge(field_id)));
+        }
+        if let Some(previous) = self.last_field_id
+            && field_id <= previous
+        {
+            return Err(WriteError::new(WriteErrorKind::FieldOrderViolation {
+                previous,
+                current: field_id,
+            }));
+        }
+        self.last_field_id = Some(field_id);
+
+        let Some(value) = value.as_relish() else {
+            return Ok(());
+        };
+        match &mut self.buffer {
+            Some(buffer) => {
+                buffer.write_byte(field_id)?;
+                buffer.write_byte(value.value_type() as u8)?;
+                value.write_value(*buffer)?;
+            }
+            None => self.content_len += 2 + value.value_length(),
+        }
+        Ok(())
+    }
+}
+
+impl StructWriter<'_> {
+    /// Returns the encoded length, including the length prefix, of a struct
+    /// whose fields are written by `fields`.
+    pub fn value_length(fields: impl FnOnce(&mut StructWriter<'_>) -> WriteResult<()>) -> usize {
+        let content_len = StructWriter::content_length(fields);
+        tagged_varint_length_size(content_len) + content_len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::io;
+
+    use bytes::BytesMut;
+
+    use crate::buf::BytesRef;
+    use crate::{
+        ParseResult, Relish, Sink, StructReader, StructWriter, TypeId, WriteError, WriteErrorKind,
+        WriteResult, to_buf_mut, to_slice, to_vec, to_writer,
+    };
+
+    #[test]
+    fn test_to_writer() {
+        let value = vec!["foo".to_string(), "bar".repeat(100)];
+        let mut output = Vec::new();
+        to_writer(&mut output, &value).unwrap();
+        assert_eq!(output, to_vec(&value).unwrap());
+    }
+
+    #[test]
+    fn test_to_writer_error() {
+        let mut buf = [0u8; 4];
+        let result = to_writer(&mut buf[..], &"Hello, Relish!".to_string());
+        assert_eq!(
+            result.unwrap_err().kind(),
+            &WriteErrorKind::Io(io::ErrorKind::WriteZero)
+        );
+    }
+
+    thread_local! {
+        static LENGTH_CALLS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// A string that counts how often its length is computed.
+    struct Counted(String);
+
+    impl Relish for Counted {
+        const TYPE: TypeId = TypeId::String;
+
+        fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+            String::parse_value(data).map(Counted)
+        }
+
+        fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> WriteResult<()> {
+            self.0.write_value(buffer)
+        }
+
+        fn value_length(&self) -> usize {
+            LENGTH_CALLS.set(LENGTH_CALLS.get() + 1);
+            self.0.value_length()
+        }
+    }
+
+    /// A struct implemented by hand, whose fields are written in the order
+    /// given by `ids`.
+    struct Manual {
+        ids: [u8; 2],
+        name: String,
+        nickname: Option<String>,
+    }
+
+    impl Manual {
+        fn write_fields<W: Sink + ?Sized>(
+            &self,
+            writer: &mut StructWriter<'_, W>,
+        ) -> WriteResult<()> {
+            writer.field(self.ids[0], &self.name)?;
+            writer.field(self.ids[1], &self.nickname)
+        }
+    }
+
+    impl Relish for Manual {
+        const TYPE: TypeId = TypeId::Struct;
+
+        fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+            let mut reader = StructReader::new(data);
+            let name = reader.field(0)?.unwrap_or_default();
+            let nickname = reader.field(1)?;
+            reader.finish()?;
+            Ok(Manual {
+                ids: [0, 1],
+                name,
+                nickname,
+            })
+        }
+
+        fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> WriteResult<()> {
+            StructWriter::write(buffer, |writer| self.write_fields(writer))
+        }
+
+        fn value_length(&self) -> usize {
+            StructWriter::value_length(|writer| self.write_fields(writer))
+        }
+    }
+
+    #[test]
+    fn test_struct_writer() {
+        let value = Manual {
+            ids: [0, 1],
+            name: "x".repeat(130),
+            nickname: Some("y".to_string()),
+        };
+        let data = to_vec(&value).unwrap();
+        assert_eq!(data, to_vec(&("x".repeat(130), "y".to_string())).unwrap());
+        assert_eq!(data.len(), 1 + value.value_length());
+        let mut output = Vec::new();
+        to_writer(&mut output, &value).unwrap();
+        assert_eq!(output, data);
+
+        // None fields are omitted
+        let value = Manual {
+            nickname: None,
+            ..value
+        };
+        let data = to_vec(&value).unwrap();
+        assert_eq!(data, to_vec(&("x".repeat(130),)).unwrap());
+        let parsed: Manual = crate::parse(data.into()).unwrap();
+        assert_eq!(parsed.nickname, None);
+
+        let value = Manual {
+            ids: [1, 1],
+            ..value
+        };
+        assert_eq!(
+            to_vec(&value).unwrap_err().kind(),
+            &WriteErrorKind::FieldOrderViolation {
+                previous: 1,
+                current: 1
+            }
+        );
+        let value = Manual {
+            ids: [0, 128],
+            ..value
+        };
+        assert_eq!(
+            to_vec(&value).unwrap_err().kind(),
+            &WriteErrorKind::FieldIdTooLarge(128)
+        );
+    }
+
+    #[test]
+    fn test_to_vec_backpatches_lengths() {
+        // Lengths either side of the 1-byte limit at every level of nesting
+        let value: Vec<Vec<(u8, Counted)>> = (120..136)
+            .map(|len| vec![(1, Counted("x".repeat(len)))])
+            .collect();
+
+        let data = to_vec(&value).unwrap();
+        assert_eq!(LENGTH_CALLS.get(), 0);
+
+        let mut output = Vec::new();
+        to_writer(&mut output, &value).unwrap();
+        assert!(LENGTH_CALLS.get() > 0);
+        assert_eq!(output, data);
+
+        // Each value is measured once to check that it fits
+        LENGTH_CALLS.set(0);
+        let mut buf = vec![0u8; data.len() + 64];
+        assert_eq!(to_slice(&value, &mut buf), Ok(data.len()));
+        assert_eq!(buf[..data.len()], data);
+        assert_eq!(LENGTH_CALLS.get(), value.len());
+
+        // Lengths are measured in a single pass before writing
+        LENGTH_CALLS.set(0);
+        let mut output = BytesMut::new();
+        to_buf_mut(&value, &mut output).unwrap();
+        assert_eq!(LENGTH_CALLS.get(), 0);
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn test_to_slice() {
+        let value = vec!["foo".to_string(), "bar".repeat(100)];
+        let expected = to_vec(&value).unwrap();
+
+        let mut buf = [0u8; 512];
+        let written = to_slice(&value, &mut buf).unwrap();
+        assert_eq!(&buf[..written], expected);
+
+        // No room to spare for the prefix reserved for the last element
+        let nested = vec![vec!["bar".repeat(100)], vec!["foo".to_string()]];
+        let data = to_vec(&nested).unwrap();
+        let mut buf = vec![0u8; data.len()];
+        assert_eq!(to_slice(&nested, &mut buf), Ok(data.len()));
+        assert_eq!(buf, data);
+
+        let mut buf = vec![0u8; expected.len() - 1];
+        assert_eq!(
+            to_slice(&value, &mut buf),
+            Err(WriteError::new(WriteErrorKind::BufferTooSmall {
+                needed: expected.len(),
+                available: expected.len() - 1,
+            }))
+        );
+        assert!(buf.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_bytes_mut() {
+        let value = vec![(1u8, "x".repeat(200)), (2, "y".to_string())];
+        let expected = to_vec(&value).unwrap();
+
+        let mut buf = BytesMut::new();
+        to_buf_mut(&value, &mut buf).unwrap();
+        assert_eq!(buf, expected);
+
+        buf.clear();
+        value.write_value(&mut buf).unwrap();
+        assert_eq!(buf, expected[1..]);
+    }
+
+    /// An array of empty arrays, with a different number of them each time
+    /// it is written.
+    struct Changing(RefCell<Vec<usize>>);
+
+    impl Changing {
+        fn next(&self) -> Vec<Vec<u8>> {
+            vec![Vec::new(); self.0.borrow_mut().remove(0)]
+        }
+    }
+
+    impl Relish for Changing {
+        const TYPE: TypeId = TypeId::Array;
+
+        fn parse_value(data: &mut BytesRef) -> ParseResult<Self> {
+            let value = Vec::<Vec<u8>>::parse_value(data)?;
+            Ok(Changing(RefCell::new(vec![value.len()])))
+        }
+
+        fn write_value<W: Sink + ?Sized>(&self, buffer: &mut W) -> WriteResult<()> {
+            self.next().write_value(buffer)
+        }
+
+        fn value_length(&self) -> usize {
+            vec![Vec::<u8>::new(); self.0.borrow()[0]].value_length()
+        }
+    }
+
+    #[test]
+    fn test_to_buf_mut_inconsistent_value() {
+        let mut buf = BytesMut::new();
+        to_buf_mut(&Changing(RefCell::new(vec![2, 2])), &mut buf).unwrap();
+        assert_eq!(buf, to_vec(&vec![Vec::<u8>::new(); 2]).unwrap());
+
+        for counts in [vec![0, 1], vec![1, 0]] {
+            assert_eq!(
+                to_buf_mut(&Changing(RefCell::new(counts)), &mut BytesMut::new()),
+                Err(WriteError::new(WriteErrorKind::InconsistentValue))
+            );
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use bytes::Bytes;
-use relish::{ParseError, ParseErrorKind, ParseResult, Relish, parse, to_vec};
+use relish::{
+    ParseError, ParseErrorKind, ParseResult, PathFrame, Relish, parse, parse_borrowed, to_vec,
+};
 
 fn assert_roundtrips<T: Relish + PartialEq + std::fmt::Debug>(cases: &[(ParseResult<T>, &[u8])]) {
     for (expected, bytes) in cases {
@@ -126,6 +128,57 @@ fn test_parse_with_unknown_fields() {
     assert_eq!(result, Ok(Partial { a: 42 }));
 }
 
+#[test]
+fn test_preserve_unknown_fields() {
+    #[derive(Debug, PartialEq, Relish)]
+    struct Newer {
+        #[relish(field_id = 0)]
+        a: u8,
+        #[relish(field_id = 1)]
+        b: String,
+        #[relish(field_id = 3)]
+        c: u8,
+        #[relish(field_id = 5)]
+        d: u32,
+    }
+
+    #[derive(Debug, PartialEq, Relish)]
+    struct Older {
+        #[relish(field_id = 0)]
+        a: u8,
+        #[relish(field_id = 3)]
+        c: u8,
+        #[relish(unknown_fields)]
+        unknown: relish::UnknownFields,
+    }
+
+    let newer = Newer {
+        a: 1,
+        b: "hi".to_string(),
+        c: 3,
+        d: 5,
+    };
+    let data = to_vec(&newer).unwrap();
+
+    let mut older: Older = parse(Bytes::from(data.clone())).unwrap();
+    assert_eq!((older.a, older.c), (1, 3));
+    assert_eq!(
+        older.unknown.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+        [1, 5]
+    );
+    assert_eq!(to_vec(&older).unwrap(), data);
+
+    older.c = 4;
+    let parsed: Newer = parse(Bytes::from(to_vec(&older).unwrap())).unwrap();
+    assert_eq!(parsed, Newer { c: 4, ..newer });
+
+    older.unknown.clear();
+    assert_eq!(
+        to_vec(&older).unwrap(),
+        [0x11, 0x0C, 0x00, 0x02, 0x01, 0x03, 0x02, 0x04]
+    );
+}
+
 #[test]
 fn test_parse_fields_not_in_order() {
     #[derive(Debug, PartialEq, Relish)]
@@ -146,7 +199,8 @@ fn test_parse_fields_not_in_order() {
         Err(ParseError::new(ParseErrorKind::FieldOrderViolation {
             previous: 1,
             current: 0
-        }))
+        })
+        .with_offset(8))
     );
 }
 
@@ -200,6 +254,54 @@ fn test_simple_enum() {
     ]);
 }
 
+#[test]
+fn test_unit_and_tuple_variants() {
+    #[derive(Debug, PartialEq, Relish)]
+    enum Status {
+        #[relish(field_id = 0)]
+        Active(()),
+        #[relish(field_id = 1)]
+        Moved((u8, u8)),
+    }
+
+    assert_roundtrips(&[
+        (Ok(Status::Active(())), &[0x12, 0x04, 0x00, 0x00]),
+        (
+            Ok(Status::Moved((3, 4))),
+            &[
+                0x12, 0x12, 0x01, 0x11, 0x0C, 0x00, 0x02, 0x03, 0x01, 0x02, 0x04,
+            ],
+        ),
+    ]);
+}
+
+#[test]
+fn test_recursive_enum() {
+    #[derive(Debug, PartialEq, Relish)]
+    enum Expr {
+        #[relish(field_id = 0)]
+        Literal(u8),
+        #[relish(field_id = 1)]
+        Negate(Box<Expr>),
+        #[relish(field_id = 2)]
+        Add(Box<(Expr, Expr)>),
+    }
+
+    assert_roundtrips(&[
+        (
+            Ok(Expr::Negate(Box::new(Expr::Literal(7)))),
+            &[0x12, 0x0C, 0x01, 0x12, 0x06, 0x00, 0x02, 0x07],
+        ),
+        (
+            Ok(Expr::Add(Box::new((Expr::Literal(1), Expr::Literal(2))))),
+            &[
+                0x12, 0x1E, 0x02, 0x11, 0x18, 0x00, 0x12, 0x06, 0x00, 0x02, 0x01, 0x01, 0x12, 0x06,
+                0x00, 0x02, 0x02,
+            ],
+        ),
+    ]);
+}
+
 #[test]
 fn test_enum_with_nested_struct() {
     #[derive(Debug, PartialEq, Relish)]
@@ -243,7 +345,7 @@ fn test_enum_unknown_variant() {
     let result: ParseResult<SimpleEnum> = parse(Bytes::from(data.to_vec()));
     assert_eq!(
         result,
-        Err(ParseError::new(ParseErrorKind::UnknownVariant(5)))
+        Err(ParseError::new(ParseErrorKind::UnknownVariant(5)).with_offset(0))
     );
 }
 
@@ -299,8 +401,170 @@ fn test_enum_with_extra_data() {
     let result: ParseResult<SimpleEnum> = parse(Bytes::from(data.to_vec()));
     assert_eq!(
         result,
-        Err(ParseError::new(ParseErrorKind::ExtraData {
-            bytes_remaining: 1
-        }))
+        Err(
+            ParseError::new(ParseErrorKind::ExtraData { bytes_remaining: 1 })
+                .with_offset(8)
+                .with_frame(PathFrame::Variant {
+                    type_name: "SimpleEnum",
+                    variant_name: "A",
+                    variant_id: 0,
+                })
+        )
+    );
+}
+
+#[test]
+fn test_borrowed_struct() {
+    use std::borrow::Cow;
+
+    #[derive(Debug, PartialEq, Relish)]
+    struct Borrowed<'a> {
+        #[relish(field_id = 0)]
+        name: &'a str,
+        #[relish(field_id = 1)]
+        data: &'a [u8],
+        #[relish(field_id = 2)]
+        label: Option<Cow<'a, str>>,
+        #[relish(field_id = 3)]
+        count: u32,
+    }
+
+    let value = Borrowed {
+        name: "hello",
+        data: &[1, 2, 3],
+        label: Some(Cow::Borrowed("x")),
+        count: 7,
+    };
+    let bytes = to_vec(&value).unwrap();
+    assert_eq!(
+        bytes,
+        [
+            0x11, 0x32, 0x00, 0x0E, 0x0A, b'h', b'e', b'l', b'l', b'o', 0x01, 0x0F, 0x08, 0x02,
+            0x01, 0x02, 0x03, 0x02, 0x0E, 0x02, b'x', 0x03, 0x04, 0x07, 0x00, 0x00, 0x00,
+        ]
+    );
+
+    let parsed: Borrowed<'_> = parse_borrowed(&bytes).unwrap();
+    assert_eq!(parsed, value);
+    assert_eq!(parsed.name.as_ptr(), bytes[5..].as_ptr());
+    assert!(matches!(parsed.label, Some(Cow::Borrowed(_))));
+
+    let missing = [0x11, 0x0C, 0x03, 0x04, 0x07, 0x00, 0x00, 0x00];
+    assert_eq!(
+        parse_borrowed::<Borrowed<'_>>(&missing),
+        Err(ParseError::new(ParseErrorKind::MissingRequiredField)
+            .with_offset(0)
+            .with_frame(PathFrame::Field {
+                type_name: "Borrowed",
+                field_name: "name",
+                field_id: 0,
+            }))
+    );
+}
+
+#[test]
+fn test_borrowed_enum() {
+    #[derive(Debug, PartialEq, Relish)]
+    enum Borrowed<'a> {
+        #[relish(field_id = 0)]
+        Name(&'a str),
+        #[relish(field_id = 1)]
+        Count(u32),
+    }
+
+    let data = [0x12, 0x10, 0x00, 0x0E, 0x0A, b'h', b'e', b'l', b'l', b'o'];
+    let parsed: Borrowed<'_> = parse_borrowed(&data).unwrap();
+    assert_eq!(parsed, Borrowed::Name("hello"));
+    assert_eq!(to_vec(&parsed).unwrap(), data);
+
+    let value = Borrowed::Count(5);
+    assert_eq!(
+        parse_borrowed::<Borrowed<'_>>(&to_vec(&value).unwrap()),
+        Ok(Borrowed::Count(5))
+    );
+}
+
+#[test]
+fn test_value_field() {
+    #[derive(Debug, PartialEq, Relish)]
+    struct Envelope {
+        #[relish(field_id = 0)]
+        kind: u8,
+        #[relish(field_id = 1)]
+        payload: relish::Value,
+    }
+
+    assert_roundtrips(&[
+        (
+            Ok(Envelope {
+                kind: 1,
+                payload: relish::Value::String("hi".to_string()),
+            }),
+            &[0x11, 0x10, 0x00, 0x02, 0x01, 0x01, 0x0E, 0x04, b'h', b'i'],
+        ),
+        (
+            Ok(Envelope {
+                kind: 2,
+                payload: relish::Value::U32(7),
+            }),
+            &[
+                0x11, 0x12, 0x00, 0x02, 0x02, 0x01, 0x04, 0x07, 0x00, 0x00, 0x00,
+            ],
+        ),
+    ]);
+}
+
+#[test]
+fn test_error_path() {
+    #[derive(Debug, PartialEq, Relish)]
+    struct Item {
+        #[relish(field_id = 0)]
+        name: String,
+        #[relish(field_id = 2)]
+        price: u64,
+    }
+
+    #[derive(Debug, PartialEq, Relish)]
+    struct Order {
+        #[relish(field_id = 1)]
+        items: Vec<Item>,
+    }
+
+    #[derive(Debug, PartialEq, Relish)]
+    struct BadItem {
+        #[relish(field_id = 2)]
+        price: u32,
+    }
+
+    #[derive(Debug, PartialEq, Relish)]
+    struct BadOrder {
+        #[relish(field_id = 1)]
+        items: Vec<BadItem>,
+    }
+
+    let data = to_vec(&BadOrder {
+        items: vec![BadItem { price: 1 }, BadItem { price: 2 }],
+    })
+    .unwrap();
+    let err = parse::<Order>(Bytes::from(data)).unwrap_err();
+    assert_eq!(
+        err.path().cloned().collect::<Vec<_>>(),
+        [
+            PathFrame::Field {
+                type_name: "Order",
+                field_name: "items",
+                field_id: 1,
+            },
+            PathFrame::Index(0),
+            PathFrame::Field {
+                type_name: "Item",
+                field_name: "price",
+                field_id: 2,
+            },
+        ]
+    );
+    assert_eq!(
+        err.to_string(),
+        "parse error at offset 8: Order.items[0].price (field 2): type mismatch: expected u64, got u32"
     );
 }